@@ -0,0 +1,266 @@
+use semver::{Version, VersionReq};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use toml;
+use version::lowest_version;
+
+/// Enumeration of errors which can occur when reading a RustSec advisory database.
+quick_error! {
+    #[derive(Debug)]
+    pub enum AdvisoryError {
+        /// The database could not be read.
+        Io(path: PathBuf, err: io::Error) {
+            description("could not read the advisory database")
+            display("Could not read `{}`: {}", path.display(), err)
+            cause(err)
+        }
+        /// An advisory is not in the expected format.
+        Parse(path: PathBuf, message: String) {
+            description("invalid advisory")
+            display("Invalid advisory `{}`: {}", path.display(), message)
+        }
+    }
+}
+
+/// A security advisory for a crate, from a checkout of the RustSec advisory database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    /// The ID of the advisory, e.g. `RUSTSEC-2019-0001`
+    pub id: String,
+    /// The crate the advisory is about
+    pub package: String,
+    /// Requirements matching the versions with a fix
+    pub patched: Vec<String>,
+    /// Requirements matching the versions that were never affected
+    pub unaffected: Vec<String>,
+}
+
+impl Advisory {
+    /// Whether the given version of the crate is affected.
+    pub fn affects(&self, version: &Version) -> bool {
+        !self.patched
+            .iter()
+            .chain(self.unaffected.iter())
+            .any(|req| VersionReq::parse(req).map_or(false, |req| req.matches(version)))
+    }
+
+    /// The smallest patched version newer than the given one, e.g. `1.8.1` for `>= 1.8.1, < 2`
+    /// and `1.7.0`. Only the lowest version of each patched requirement is a candidate.
+    pub fn first_patched_version(&self, version: &Version) -> Option<Version> {
+        self.patched
+            .iter()
+            .filter_map(|req| {
+                lowest_version(req).and_then(|lowest| {
+                    let matches =
+                        VersionReq::parse(req).map_or(false, |req| req.matches(&lowest));
+                    if matches && lowest > *version {
+                        Some(lowest)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .min()
+    }
+}
+
+/// Read all advisories from a checkout of the RustSec advisory database.
+///
+/// Both the current format, Markdown files starting with a fenced TOML block, and the older plain
+/// TOML files are understood. Only `crates/` is read if it exists, and Markdown files without a
+/// fenced TOML block are skipped. Withdrawn and informational advisories (like unmaintained
+/// crates) are left out.
+pub fn read_advisories(path: &Path) -> Result<Vec<Advisory>, AdvisoryError> {
+    let crates = path.join("crates");
+    let root = if crates.is_dir() { crates } else { path.to_owned() };
+
+    let mut advisories = Vec::new();
+    for file in find_advisory_files(&root)? {
+        let mut content = String::new();
+        File::open(&file)
+            .and_then(|mut f| f.read_to_string(&mut content))
+            .map_err(|err| AdvisoryError::Io(file.clone(), err))?;
+
+        let is_markdown = file.extension().map_or(false, |ext| ext == "md");
+        if let Some(advisory) = parse_advisory(&content, is_markdown)
+            .map_err(|message| AdvisoryError::Parse(file.clone(), message))?
+        {
+            advisories.push(advisory);
+        }
+    }
+
+    Ok(advisories)
+}
+
+/// Find all `.md` and `.toml` files at a path, recursively.
+fn find_advisory_files(path: &Path) -> Result<Vec<PathBuf>, AdvisoryError> {
+    let io_error = |err| AdvisoryError::Io(path.to_owned(), err);
+    if fs::metadata(path).map_err(&io_error)?.is_file() {
+        let is_advisory = path.extension().map_or(false, |ext| ext == "md" || ext == "toml");
+        return Ok(if is_advisory {
+            vec![path.to_owned()]
+        } else {
+            vec![]
+        });
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path).map_err(&io_error)? {
+        files.extend(find_advisory_files(&entry.map_err(&io_error)?.path())?);
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Parse an advisory, `None` if it was withdrawn or is only informational, or if a Markdown file
+/// is no advisory at all.
+fn parse_advisory(content: &str, is_markdown: bool) -> Result<Option<Advisory>, String> {
+    let content = if is_markdown {
+        let mut lines = content.lines().skip_while(|line| line.trim() != "```toml");
+        // Other documents, like the README, have no front matter.
+        if lines.next().is_none() {
+            return Ok(None);
+        }
+        lines
+            .take_while(|line| line.trim() != "```")
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        content.to_owned()
+    };
+    let data: toml::Value = content.parse().map_err(|err: toml::de::Error| err.to_string())?;
+
+    let advisory = data.get("advisory")
+        .ok_or_else(|| "missing `[advisory]`".to_owned())?;
+    if advisory.get("withdrawn").is_some() || advisory.get("informational").is_some() {
+        return Ok(None);
+    }
+
+    let get = |key| {
+        advisory
+            .get(key)
+            .and_then(toml::Value::as_str)
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| format!("missing `advisory.{}`", key))
+    };
+    // `[versions] patched` in the current format, `advisory.patched_versions` in the older one
+    let get_requirements = |key: &str, old_key: &str| -> Result<Vec<String>, String> {
+        let requirements = data.get("versions")
+            .and_then(|versions| versions.get(key))
+            .or_else(|| advisory.get(old_key))
+            .and_then(toml::Value::as_array)
+            .map_or(&[][..], |requirements| &requirements[..]);
+        requirements
+            .iter()
+            .map(|req| {
+                let req = req.as_str().ok_or_else(|| format!("invalid `{}`", key))?;
+                VersionReq::parse(req)
+                    .map_err(|err| format!("invalid requirement `{}`: {}", req, err))?;
+                Ok(req.to_owned())
+            })
+            .collect()
+    };
+
+    Ok(Some(Advisory {
+        id: get("id")?,
+        package: get("package")?,
+        patched: get_requirements("patched", "patched_versions")?,
+        unaffected: get_requirements("unaffected", "unaffected_versions")?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn parses_markdown_advisories() {
+        let advisory = parse_advisory(
+            r#"```toml
+[advisory]
+id = "RUSTSEC-2019-0001"
+package = "ammonia"
+date = "2019-04-27"
+
+[versions]
+patched = [">= 2.1.0"]
+unaffected = ["< 1.0.0"]
+```
+
+# Uncontrolled recursion leads to abort in HTML serialization
+"#,
+            true,
+        ).unwrap()
+            .unwrap();
+
+        assert_eq!(advisory.id, "RUSTSEC-2019-0001");
+        assert_eq!(advisory.package, "ammonia");
+        assert_eq!(advisory.patched, vec![">= 2.1.0"]);
+        assert_eq!(advisory.unaffected, vec!["< 1.0.0"]);
+    }
+
+    #[test]
+    fn parses_toml_advisories() {
+        let advisory = parse_advisory(
+            r#"
+[advisory]
+id = "RUSTSEC-2018-0003"
+package = "smallvec"
+patched_versions = [">= 0.6.3", "^0.3.4, < 0.4"]
+"#,
+            false,
+        ).unwrap()
+            .unwrap();
+
+        assert_eq!(advisory.patched, vec![">= 0.6.3", "^0.3.4, < 0.4"]);
+        assert!(advisory.unaffected.is_empty());
+    }
+
+    #[test]
+    fn skips_withdrawn_and_informational_advisories() {
+        let parse = |extra| {
+            parse_advisory(
+                &format!("[advisory]\nid = \"X\"\npackage = \"foo\"\n{}", extra),
+                false,
+            ).unwrap()
+        };
+
+        assert!(parse("").is_some());
+        assert!(parse("withdrawn = \"2020-01-01\"").is_none());
+        assert!(parse("informational = \"unmaintained\"").is_none());
+        assert!(parse_advisory("[advisory]\nid = \"X\"", false).is_err());
+        assert!(parse_advisory("# No TOML", true).unwrap().is_none());
+    }
+
+    #[test]
+    fn finds_affected_and_patched_versions() {
+        let advisory = Advisory {
+            id: "RUSTSEC-2018-0003".to_owned(),
+            package: "smallvec".to_owned(),
+            patched: vec![">= 0.6.3".to_owned(), "^0.3.4".to_owned()],
+            unaffected: vec!["< 0.3.0".to_owned()],
+        };
+
+        assert!(!advisory.affects(&version("0.2.9")));
+        assert!(advisory.affects(&version("0.3.1")));
+        assert!(!advisory.affects(&version("0.3.5")));
+        assert!(advisory.affects(&version("0.6.2")));
+        assert!(!advisory.affects(&version("0.6.3")));
+
+        assert_eq!(
+            advisory.first_patched_version(&version("0.3.1")),
+            Some(version("0.3.4"))
+        );
+        assert_eq!(
+            advisory.first_patched_version(&version("0.5.0")),
+            Some(version("0.6.3"))
+        );
+        assert_eq!(advisory.first_patched_version(&version("0.7.0")), None);
+    }
+}
@@ -1,9 +1,9 @@
 //! Handle `cargo add` arguments
 
-use cargo_edit::Dependency;
-use cargo_edit::{get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from_path,
-                 get_latest_dependency};
-use semver;
+use cargo_edit::{Dependency, Manifest};
+use cargo_edit::{get_compatible_dependency, get_crate_name_from_github, get_crate_name_from_gitlab,
+                 get_crate_name_from_path, get_latest_dependency, validate_target,
+                 PublishedBefore};
 use std::error::Error;
 use std::path::PathBuf;
 
@@ -24,10 +24,14 @@ pub struct Args {
     pub flag_git: Option<String>,
     /// Crate directory path
     pub flag_path: Option<PathBuf>,
-    /// Crate directory path
+    /// Target platform
     pub flag_target: Option<String>,
     /// Optional dependency
     pub flag_optional: bool,
+    /// Features to activate
+    pub flag_features: Option<String>,
+    /// Features of this crate that enable the optional dependency
+    pub flag_in_feature: Option<String>,
     /// `Cargo.toml` path
     pub flag_manifest_path: Option<PathBuf>,
     /// `--version`
@@ -36,38 +40,164 @@ pub struct Args {
     pub flag_upgrade: Option<String>,
     /// '--fetch-prereleases'
     pub flag_allow_prerelease: bool,
+    /// `--before <date>`
+    pub flag_before: Option<String>,
+    /// `--prefer-locked`
+    pub flag_prefer_locked: bool,
+    /// `--move`
+    pub flag_move: bool,
+    /// `--extern`
+    pub flag_extern: bool,
+    /// `--macro-use`
+    pub flag_macro_use: bool,
 }
 
 impl Args {
     /// Get dependency section
-    pub fn get_section(&self) -> Vec<String> {
-        if self.flag_dev {
-            vec!["dev-dependencies".to_owned()]
+    pub fn get_section(&self) -> Result<Vec<String>, Box<Error>> {
+        let kind = if self.flag_dev {
+            "dev-dependencies"
         } else if self.flag_build {
-            vec!["build-dependencies".to_owned()]
-        } else if let Some(ref target) = self.flag_target {
-            if target.is_empty() {
-                panic!("Target specification may not be empty");
-            }
-            vec![
+            "build-dependencies"
+        } else {
+            "dependencies"
+        };
+
+        if let Some(ref target) = self.flag_target {
+            validate_target(target)?;
+            Ok(vec![
                 "target".to_owned(),
-                target.clone(),
-                "dependencies".to_owned(),
-            ]
+                target.trim().to_owned(),
+                kind.to_owned(),
+            ])
         } else {
-            vec!["dependencies".to_owned()]
+            Ok(vec![kind.to_owned()])
+        }
+    }
+
+    /// Get the features to activate, as given by `--features`
+    pub fn get_features(&self) -> Option<Vec<String>> {
+        self.flag_features.as_ref().map(|features| split_features(features))
+    }
+
+    /// Get the features that should enable the optional dependency, as given by `--in-feature`
+    pub fn get_in_features(&self) -> Result<Option<Vec<String>>, Box<Error>> {
+        match self.flag_in_feature {
+            Some(_) if !self.flag_optional => Err(From::from(
+                "`--in-feature` can only be used together with `--optional`.",
+            )),
+            Some(ref features) => Ok(Some(split_features(features))),
+            None => Ok(None),
+        }
+    }
+
+    /// Check that `--extern` is only used for dependencies the crate root can always use
+    pub fn check_extern(&self) -> Result<(), Box<Error>> {
+        let declare = self.flag_extern || self.flag_macro_use;
+        if declare && (self.flag_dev || self.flag_build || self.flag_target.is_some()) {
+            Err(From::from(
+                "`--extern` can only be used for dependencies that are always available to the \
+                 crate root, not with `--dev`, `--build` or `--target`.",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that `--move` is only combined with what can be applied to an existing entry
+    pub fn check_move(&self) -> Result<(), Box<Error>> {
+        let has_version = self.arg_crates.iter().any(|name| crate_name_has_version(name)) ||
+            crate_name_has_version(&self.arg_crate);
+        if has_version || self.flag_vers.is_some() || self.flag_git.is_some() ||
+            self.flag_path.is_some()
+        {
+            Err(From::from(
+                "`--move` keeps the version and source of a dependency, they cannot be given \
+                 with it.",
+            ))
+        } else if self.flag_in_feature.is_some() || self.flag_extern || self.flag_macro_use {
+            Err(From::from(
+                "`--move` cannot be combined with `--in-feature`, `--extern` or `--macro-use`.",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the attributes for the `extern crate` declaration of a dependency, if one should be
+    /// added to the crate root
+    ///
+    /// An optional dependency is declared under the first feature given with `--in-feature`, or
+    /// else a feature of `manifest` that enables it with `dep:`. Only without one does it have
+    /// an implicit feature of its own name.
+    pub fn get_extern_attributes(
+        &self,
+        manifest: &Manifest,
+        dep: &Dependency,
+    ) -> Result<Option<Vec<String>>, Box<Error>> {
+        self.check_extern()?;
+        if !self.flag_extern && !self.flag_macro_use {
+            return Ok(None);
+        }
+
+        let mut attributes = vec![];
+        if self.flag_optional {
+            let feature = self.get_in_features()?
+                .and_then(|features| features.into_iter().next())
+                .or_else(|| manifest.find_enabling_feature(&dep.name))
+                .unwrap_or_else(|| dep.name.clone());
+            attributes.push(format!("#[cfg(feature = \"{}\")]", feature));
+        }
+        if self.flag_macro_use {
+            attributes.push("#[macro_use]".to_owned());
+        }
+        Ok(Some(attributes))
+    }
+
+    /// Get the names of the crates to add, without any version given with them
+    pub fn get_crate_names(&self) -> Vec<String> {
+        let crates = if self.arg_crates.is_empty() {
+            vec![self.arg_crate.clone()]
+        } else {
+            self.arg_crates.clone()
+        };
+
+        crates
+            .iter()
+            .map(|name| name.splitn(2, '@').next().unwrap_or("").to_owned())
+            .collect()
+    }
+
+    /// Get the date before which versions must have been published, as given by `--before`
+    pub fn get_before(&self) -> Result<Option<PublishedBefore>, Box<Error>> {
+        match self.flag_before {
+            Some(ref date) => Ok(Some(date.parse()?)),
+            None => Ok(None),
         }
     }
 
     /// Build dependencies from arguments
     pub fn parse_dependencies(&self) -> Result<Vec<Dependency>, Box<Error>> {
+        let before = self.get_before()?;
+        let before = before.as_ref();
+        let locked = self.open_locked()?;
+        let locked = locked.as_ref();
+
         if !self.arg_crates.is_empty() {
+            if self.flag_features.is_some() {
+                return Err(From::from(
+                    "`--features` can only be used when adding a single crate.",
+                ));
+            }
+
             let mut result = Vec::<Dependency>::new();
             for arg_crate in &self.arg_crates {
                 let le_crate = if crate_name_has_version(arg_crate) {
-                    parse_crate_name_with_version(arg_crate)?
+                    self.parse_crate_name_with_version(arg_crate, before)?
+                } else if let Some(dep) = self.get_locked_dependency(locked, arg_crate) {
+                    dep
                 } else {
-                    get_latest_dependency(arg_crate, self.flag_allow_prerelease)?
+                    get_latest_dependency(arg_crate, self.flag_allow_prerelease, before)?
                 }.set_optional(self.flag_optional);
 
                 result.push(le_crate);
@@ -77,8 +207,9 @@ impl Args {
 
         if crate_name_has_version(&self.arg_crate) {
             return Ok(vec![
-                parse_crate_name_with_version(&self.arg_crate)?
-                    .set_optional(self.flag_optional),
+                self.parse_crate_name_with_version(&self.arg_crate, before)?
+                    .set_optional(self.flag_optional)
+                    .set_features(self.get_features()),
             ]);
         }
 
@@ -87,30 +218,136 @@ impl Args {
             let dependency = Dependency::new(&self.arg_crate);
 
             if let Some(ref version) = self.flag_vers {
-                semver::VersionReq::parse(version)?;
-                dependency.set_version(version)
+                self.resolve_requirement(&self.arg_crate, version, before)?
             } else if let Some(ref repo) = self.flag_git {
                 dependency.set_git(repo)
             } else if let Some(ref path) = self.flag_path {
                 dependency.set_path(path.to_str().unwrap())
+            } else if let Some(dep) = self.get_locked_dependency(locked, &self.arg_crate) {
+                dep
             } else {
-                let dep = get_latest_dependency(&self.arg_crate, self.flag_allow_prerelease)?;
-                let v = format!(
-                    "{prefix}{version}",
-                    prefix = self.get_upgrade_prefix().unwrap_or(""),
-                    // If version is unavailable `get_latest_dependency` must have
-                    // returned `Err(FetchVersionError::GetVersion)`
-                    version = dep.version().unwrap_or_else(|| unreachable!())
-                );
-                dep.set_version(&v)
+                let dep =
+                    get_latest_dependency(&self.arg_crate, self.flag_allow_prerelease, before)?;
+                self.set_upgrade_prefix(dep)
             }
         } else {
             parse_crate_name_from_uri(&self.arg_crate)?
-        }.set_optional(self.flag_optional);
+        }.set_optional(self.flag_optional)
+            .set_features(self.get_features());
 
         Ok(vec![dependency])
     }
 
+    /// Open what `--prefer-locked` reuses versions from, `None` if it is not enabled.
+    ///
+    /// It can also be enabled with `prefer-locked = true` in `[package.metadata.cargo-add]` or
+    /// `[workspace.metadata.cargo-add]`, of the manifest or of the root of its workspace.
+    fn open_locked(&self) -> Result<Option<Locked>, Box<Error>> {
+        let manifest = Manifest::open(&self.flag_manifest_path)?;
+        let root = Manifest::find_workspace_root(&self.flag_manifest_path)?;
+        let prefer_locked = self.flag_prefer_locked ||
+            manifest
+                .get_tool_metadata_in_workspace(root.as_ref().map(|root| &root.1), "cargo-add")
+                .get("prefer-locked")
+                .and_then(|prefer| prefer.as_bool())
+                .unwrap_or(false);
+        if !prefer_locked {
+            return Ok(None);
+        }
+
+        let mut manifests = vec![manifest];
+        manifests.extend(Manifest::open_workspace_members(&self.flag_manifest_path)?);
+        Ok(Some(Locked {
+            lock_file: Manifest::open_lock_file(&self.flag_manifest_path).ok(),
+            manifests: manifests,
+        }))
+    }
+
+    /// Look up the version of a crate the workspace already uses, if `--prefer-locked` asks for
+    /// it.
+    ///
+    /// The newest version resolved in `Cargo.lock` is used first, then a requirement the manifest
+    /// or another member of its workspace already has. Returns `None` if the crate is new.
+    fn get_locked_dependency(
+        &self,
+        locked: Option<&Locked>,
+        crate_name: &str,
+    ) -> Option<Dependency> {
+        let locked = match locked {
+            Some(locked) => locked,
+            None => return None,
+        };
+
+        let version = locked
+            .lock_file
+            .as_ref()
+            .and_then(|lock| lock.get_locked_versions(crate_name).into_iter().next());
+        if let Some(version) = version {
+            println!("Using `{}` v{} from Cargo.lock", crate_name, version);
+            let dep = Dependency::new(crate_name).set_version(&version.to_string());
+            return Some(self.set_upgrade_prefix(dep));
+        }
+
+        let required = locked
+            .manifests
+            .iter()
+            .filter_map(|manifest| manifest.get_requirement(crate_name))
+            .next();
+        required.map(|version| {
+            println!(
+                "Using `{}` requirement `{}` already used in the workspace",
+                crate_name,
+                version
+            );
+            Dependency::new(crate_name).set_version(&version)
+        })
+    }
+
+    /// Parse a crate given as `name@requirement`, see `resolve_requirement`.
+    fn parse_crate_name_with_version(
+        &self,
+        name: &str,
+        before: Option<&PublishedBefore>,
+    ) -> Result<Dependency, Box<Error>> {
+        assert!(crate_name_has_version(name));
+
+        let xs: Vec<_> = name.splitn(2, '@').collect();
+        self.resolve_requirement(xs[0], xs[1], before)
+    }
+
+    /// Check that a published version matches the requirement the user gave.
+    ///
+    /// A bare version like `0.3` is resolved to the newest version matching it, prefixed as
+    /// chosen by `--upgrade`. A requirement with an explicit operator like `=1.2.3` or `~0.3` is
+    /// kept as it was given.
+    fn resolve_requirement(
+        &self,
+        name: &str,
+        version_req: &str,
+        before: Option<&PublishedBefore>,
+    ) -> Result<Dependency, Box<Error>> {
+        let dep =
+            get_compatible_dependency(name, version_req, self.flag_allow_prerelease, before)?;
+
+        if requirement_has_operator(version_req) {
+            Ok(dep.set_version(version_req.trim()))
+        } else {
+            Ok(self.set_upgrade_prefix(dep))
+        }
+    }
+
+    /// Prefix the version that was looked up in the registry as chosen by `--upgrade`.
+    fn set_upgrade_prefix(&self, dep: Dependency) -> Dependency {
+        let v = format!(
+            "{prefix}{version}",
+            prefix = self.get_upgrade_prefix().unwrap_or(""),
+            // If version is unavailable `get_latest_dependency` must have
+            // returned `Err(FetchVersionError::GetVersion)`
+            version = dep.version().unwrap_or_else(|| unreachable!())
+        );
+        dep.set_version(&v)
+    }
+
     fn get_upgrade_prefix(&self) -> Option<&'static str> {
         self.flag_upgrade.clone().and_then(
             |flag| match flag.to_uppercase().as_ref() {
@@ -130,6 +367,14 @@ impl Args {
     }
 }
 
+/// What `--prefer-locked` reuses versions from
+struct Locked {
+    /// `Cargo.lock`, if there is one
+    lock_file: Option<Manifest>,
+    /// The manifest, followed by the ones of the other members of its workspace
+    manifests: Vec<Manifest>,
+}
+
 impl Default for Args {
     fn default() -> Args {
         Args {
@@ -142,14 +387,30 @@ impl Default for Args {
             flag_path: None,
             flag_target: None,
             flag_optional: false,
+            flag_features: None,
+            flag_in_feature: None,
             flag_manifest_path: None,
             flag_version: false,
             flag_upgrade: None,
             flag_allow_prerelease: false,
+            flag_before: None,
+            flag_prefer_locked: false,
+            flag_move: false,
+            flag_extern: false,
+            flag_macro_use: false,
         }
     }
 }
 
+/// Split a space or comma separated list of features
+fn split_features(features: &str) -> Vec<String> {
+    features
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|feature| !feature.is_empty())
+        .map(|feature| feature.to_owned())
+        .collect()
+}
+
 fn crate_name_has_version(name: &str) -> bool {
     name.contains('@')
 }
@@ -171,14 +432,10 @@ fn crate_name_is_path(name: &str) -> bool {
     name.contains('.') || name.contains('/') || name.contains('\\')
 }
 
-fn parse_crate_name_with_version(name: &str) -> Result<Dependency, Box<Error>> {
-    assert!(crate_name_has_version(name));
-
-    let xs: Vec<_> = name.splitn(2, '@').collect();
-    let (name, version) = (xs[0], xs[1]);
-    semver::VersionReq::parse(version)?;
-
-    Ok(Dependency::new(name).set_version(version))
+/// Whether a version requirement says how to match versions, instead of being a bare version
+fn requirement_has_operator(version_req: &str) -> bool {
+    version_req.trim().starts_with(|c| "=~^<>".contains(c)) || version_req.contains(',') ||
+        version_req.contains('*')
 }
 
 fn parse_crate_name_from_uri(name: &str) -> Result<Dependency, Box<Error>> {
@@ -206,21 +463,78 @@ fn parse_crate_name_from_uri(name: &str) -> Result<Dependency, Box<Error>> {
 mod tests {
     use cargo_edit::Dependency;
     use super::*;
+    use std::env;
 
     #[test]
     fn test_dependency_parsing() {
+        // Do not look up the version on crates.io.
+        env::set_var("CARGO_IS_TEST", "1");
+
+        let parse = |version: &str, upgrade: Option<&str>| {
+            let args = Args {
+                arg_crate: "demo".to_owned(),
+                flag_vers: Some(version.to_owned()),
+                flag_upgrade: upgrade.map(ToOwned::to_owned),
+                ..Args::default()
+            };
+            args.parse_dependencies().unwrap()
+        };
+
+        assert_eq!(
+            parse("0.4.2", None),
+            vec![Dependency::new("demo").set_version("0.4.2")]
+        );
+        // A bare version is prefixed as chosen by `--upgrade`, one with an operator is kept
+        assert_eq!(
+            parse("0.4.2", Some("patch")),
+            vec![Dependency::new("demo").set_version("~0.4.2")]
+        );
+        assert_eq!(
+            parse("=1.2.3", Some("patch")),
+            vec![Dependency::new("demo").set_version("=1.2.3")]
+        );
+        assert_eq!(
+            parse(" ~0.3", Some("none")),
+            vec![Dependency::new("demo").set_version("~0.3")]
+        );
+    }
+
+    #[test]
+    fn test_features_parsing() {
+        // Do not look up the version on crates.io.
+        env::set_var("CARGO_IS_TEST", "1");
+
         let args = Args {
             arg_crate: "demo".to_owned(),
             flag_vers: Some("0.4.2".to_owned()),
+            flag_features: Some("serde, std nightly".to_owned()),
             ..Args::default()
         };
 
         assert_eq!(
             args.parse_dependencies().unwrap(),
-            vec![Dependency::new("demo").set_version("0.4.2")]
+            vec![
+                Dependency::new("demo")
+                    .set_version("0.4.2")
+                    .set_features(Some(vec![
+                        "serde".to_owned(),
+                        "std".to_owned(),
+                        "nightly".to_owned(),
+                    ])),
+            ]
         );
     }
 
+    #[test]
+    fn test_requirement_operators() {
+        assert!(!requirement_has_operator("0.4.2"));
+        assert!(!requirement_has_operator("1.0.0-beta.1"));
+        assert!(requirement_has_operator("=1.2.3"));
+        assert!(requirement_has_operator(" ~0.3"));
+        assert!(requirement_has_operator(">=1.0, <2.0"));
+        assert!(requirement_has_operator("1.*"));
+    }
+
     #[test]
     #[cfg(feature = "test-external-apis")]
     fn test_repo_as_arg_parsing() {
@@ -258,4 +572,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_section_with_target() {
+        let args = Args {
+            flag_dev: true,
+            flag_target: Some("cfg(unix)".to_owned()),
+            ..Args::default()
+        };
+        assert_eq!(
+            args.get_section().unwrap(),
+            vec!["target", "cfg(unix)", "dev-dependencies"]
+        );
+
+        let args = Args {
+            flag_build: true,
+            flag_target: Some("cfg(windows".to_owned()),
+            ..Args::default()
+        };
+        assert!(args.get_section().is_err());
+    }
 }
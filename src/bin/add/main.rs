@@ -15,10 +15,12 @@ extern crate serde_json;
 
 use std::error::Error;
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
 
 extern crate cargo_edit;
-use cargo_edit::Manifest;
+use cargo_edit::{add_extern_crate_to_file, find_crate_roots, format_table_path,
+                 get_crate_features, CrateFeatures, Dependency, Manifest};
 
 extern crate regex;
 
@@ -35,7 +37,10 @@ Usage:
 Specify what crate to add:
     --vers <ver>            Specify the version to grab from the registry (crates.io).
                             You can also specify versions as part of the name, e.g
-                            `cargo add bitflags@0.3.2`.
+                            `cargo add bitflags@0.3.2`. A bare version like `0.3` is resolved
+                            to the newest version matching it, which is then written as
+                            chosen by `--upgrade`. A requirement with an operator, like
+                            `~0.3` or `=1.2.3`, is checked and kept as it is.
     --git <uri>             Specify a git repository to download the crate from.
     --path <uri>            Specify the path the crate should be loaded from.
 
@@ -44,16 +49,40 @@ Specify where to add the crate:
     -B --build              Add crate as build dependency.
     --optional              Add as an optional dependency (for use in features). This does not work
                             for `dev-dependencies` or `build-dependencies`.
-    --target <target>       Add as dependency to the given target platform. This does not work
-                            for `dev-dependencies` or `build-dependencies`.
+    --features <features>   Space or comma separated list of features to activate. The features
+                            are checked against the ones published on crates.io.
+    --in-feature <features>
+                            Space or comma separated list of features of your crate that should
+                            enable the optional dependency. Missing features are created in
+                            `[features]`. Use `default` to enable the dependency by default.
+    --target <target>       Add as dependency to the given target platform. Combine with `--dev` or
+                            `--build` to add a target-specific development or build dependency. The
+                            target must be a `cfg(...)` expression, a target triple known to
+                            `rustc`, or the path to a custom target specification (`.json`).
+    --extern                Also declare the crate with `extern crate` in the crate root
+                            (`src/lib.rs`, or `src/main.rs` if there is no library). Optional
+                            dependencies are declared behind `#[cfg(feature = "<crate>")]`.
+    --macro-use             Like `--extern`, with `#[macro_use]` on the declaration.
 
 Options:
+    --move                  Move crates that are already dependencies in another section (e.g.
+                            `dependencies`) to the one selected by `--dev`, `--build` and
+                            `--target`, keeping their version, source, features and other keys.
+                            Renamed dependencies are found by their `package` name too. Only
+                            `--optional` and `--features` can be applied to the moved entries.
     --upgrade=<method>      Choose method of semantic version upgrade. Must be one of
                             "none" (exact version), "patch" (`~` modifier), "minor"
                             (`^` modifier, default), or "all" (`>=`).
     --manifest-path=<path>  Path to the manifest to add a dependency to.
     --allow-prerelease      Include prerelease versions when fetching from crates.io (e.g.
                             '0.6.0-alpha'). Defaults to false.
+    --before <date>         Only consider versions published on crates.io before the given date
+                            (`YYYY-MM-DD`, UTC), e.g. to match what was available back then.
+    --prefer-locked         When no version is specified, use the version already resolved in
+                            `Cargo.lock`, or the requirement another section or workspace member
+                            already has, before asking crates.io. Can also be enabled with
+                            `prefer-locked = true` in `[package.metadata.cargo-add]` or
+                            `[workspace.metadata.cargo-add]`, also of the workspace root.
     -h --help               Show this help page.
     -V --version            Show version.
 
@@ -67,21 +96,255 @@ crates.io registry suggests. One goal of `cargo add` is to prevent you from usin
 dependencies (version set to "*").
 "#;
 
+/// Look up the features a registry dependency declares, and check the requested ones against them.
+///
+/// The features are those of the version the requirement was resolved to, so `--before` and
+/// `--allow-prerelease` are taken into account. Dependencies from git or a local path are not
+/// checked. If no features were requested, failing to look up the features is not an error.
+fn check_features(args: &Args, dep: &Dependency) -> Result<Option<CrateFeatures>, Box<Error>> {
+    let version = match dep.version() {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+
+    let before = args.get_before()?;
+    let features =
+        get_crate_features(&dep.name, version, args.flag_allow_prerelease, before.as_ref());
+    match (features, dep.features()) {
+        (Ok(features), requested) => {
+            features.validate(requested.unwrap_or(&[]))?;
+            Ok(Some(features))
+        }
+        (Err(err), Some(_)) => Err(From::from(err)),
+        (Err(_), None) => Ok(None),
+    }
+}
+
+/// Print which features of a dependency are activated, and which others are available.
+fn print_features(dep: &Dependency, features: &CrateFeatures) {
+    let mut requested = vec!["default".to_owned()];
+    requested.extend(dep.features().unwrap_or(&[]).iter().cloned());
+
+    let activated = features.activated(&requested);
+    let available = features.available(&activated);
+
+    if activated.is_empty() && available.is_empty() {
+        return;
+    }
+
+    println!("`{}` v{} features:", features.name, features.version);
+    for feature in &activated {
+        println!("    + {}", feature);
+    }
+    for feature in &available {
+        println!("    - {}", feature);
+    }
+}
+
+/// Warn about dependencies that are also present in other sections than the one they were
+/// added to.
+fn warn_duplicates(manifest: &Manifest, section: &[String], deps: &[Dependency]) {
+    for dep in deps {
+        let others: Vec<_> = manifest
+            .find_dependency_entries(&dep.name)
+            .into_iter()
+            .filter(|&(ref other, _)| other.as_slice() != section)
+            .map(|(other, _)| format!("`{}`", format_table_path(&other)))
+            .collect();
+
+        if !others.is_empty() {
+            println!(
+                "WARN: `{}` is also a dependency in {}. Use `--move` to move it instead.",
+                dep.name,
+                others.join(", ")
+            );
+        }
+    }
+}
+
+/// Move crates from the section they are currently in to `section`.
+///
+/// The entries are moved as they are, only `--optional` and `--features` are applied on top.
+fn move_dependencies(
+    args: &Args,
+    manifest: &mut Manifest,
+    section: &[String],
+) -> Result<(), Box<Error>> {
+    args.check_move()?;
+
+    for name in args.get_crate_names() {
+        let sources: Vec<_> = manifest
+            .find_dependency_entries(&name)
+            .into_iter()
+            .filter(|&(ref other, _)| other.as_slice() != section)
+            .collect();
+
+        let (source, key) = match sources.len() {
+            0 => {
+                return Err(From::from(format!(
+                    "The dependency `{}` could not be found in any other section.",
+                    name
+                )))
+            }
+            1 => (&sources[0].0, &sources[0].1),
+            _ => {
+                let sources: Vec<_> = sources
+                    .iter()
+                    .map(|&(ref source, _)| format!("`{}`", format_table_path(source)))
+                    .collect();
+                return Err(From::from(format!(
+                    "The dependency `{}` is present in several sections ({}). Remove it from \
+                     the ones it should not be moved from first.",
+                    name,
+                    sources.join(", ")
+                )));
+            }
+        };
+
+        manifest.move_dependency(source, section, key)?;
+        println!(
+            "Moved `{}` from `{}` to `{}`",
+            key,
+            format_table_path(source),
+            format_table_path(section)
+        );
+
+        let entry = manifest.get_table(section)?.get_mut(key).ok_or_else(|| {
+            format!("The dependency `{}` was lost while moving it.", key)
+        })?;
+        merge_moved_entry(args, entry);
+
+        // Cargo does not allow optional development dependencies.
+        if section.last().map(String::as_str) == Some("dev-dependencies") {
+            if let Some(entry) = entry.as_table_mut() {
+                if entry.remove("optional").is_some() {
+                    println!(
+                        "WARN: `{}` is no longer optional, development dependencies cannot be",
+                        key
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Set `optional` and add the features given on the command line to a moved entry, keeping its
+/// version and source as they are.
+fn merge_moved_entry(args: &Args, entry: &mut toml::Value) {
+    let features = args.get_features();
+    if !args.flag_optional && features.is_none() {
+        return;
+    }
+
+    if let toml::Value::String(ref version) = entry.clone() {
+        let mut table = toml::value::Table::new();
+        table.insert("version".to_owned(), toml::Value::String(version.clone()));
+        *entry = toml::Value::Table(table);
+    }
+    let table = match entry.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+
+    if args.flag_optional {
+        table.insert("optional".to_owned(), toml::Value::Boolean(true));
+    }
+    if let Some(features) = features {
+        let mut merged = table
+            .get("features")
+            .and_then(toml::Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for feature in features {
+            let feature = toml::Value::String(feature);
+            if !merged.contains(&feature) {
+                merged.push(feature);
+            }
+        }
+        table.insert("features".to_owned(), toml::Value::Array(merged));
+    }
+}
+
+/// Declare dependencies with `extern crate` in the crate root.
+fn add_extern_crates(
+    args: &Args,
+    manifest: &Manifest,
+    manifest_path: &Path,
+    deps: &[Dependency],
+) -> Result<(), Box<Error>> {
+    let crate_root = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+
+    for dep in deps {
+        let attributes = match args.get_extern_attributes(manifest, dep)? {
+            Some(attributes) => attributes,
+            None => continue,
+        };
+        let roots = find_crate_roots(manifest, crate_root);
+        let root = roots.first().ok_or_else(|| {
+            format!(
+                "Could not find a crate root to declare `{}` in (`src/lib.rs` or `src/main.rs`).",
+                dep.name
+            )
+        })?;
+
+        let ident = dep.name.replace('-', "_");
+        if add_extern_crate_to_file(root, &dep.name, &attributes)? {
+            println!("Added `extern crate {};` to `{}`", ident, root.display());
+        } else {
+            println!("`{}` already declares `extern crate {};`", root.display(), ident);
+        }
+    }
+
+    Ok(())
+}
+
 fn handle_add(args: &Args) -> Result<(), Box<Error>> {
     let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
     let mut manifest = Manifest::open(&manifest_path)?;
+    let section = args.get_section()?;
+
+    if args.flag_move {
+        move_dependencies(args, &mut manifest, &section)?;
+
+        let mut file = Manifest::find_file(&manifest_path)?;
+        return manifest.write_to_file(&mut file);
+    }
+
+    let in_features = args.get_in_features()?;
+    args.check_extern()?;
     let deps = &args.parse_dependencies()?;
+    let features = deps.iter()
+        .map(|dep| check_features(args, dep))
+        .collect::<Result<Vec<_>, _>>()?;
 
     deps.iter()
-        .map(|dep| manifest.insert_into_table(&args.get_section(), dep))
+        .map(|dep| manifest.insert_into_table(&section, dep))
         .collect::<Result<Vec<_>, _>>()
         .map_err(|err| {
             println!("Could not edit `Cargo.toml`.\n\nERROR: {}", err);
             err
         })?;
 
+    if let Some(ref in_features) = in_features {
+        for dep in deps {
+            manifest.add_to_features(&dep.name, in_features)?;
+        }
+    }
+
     let mut file = Manifest::find_file(&manifest_path)?;
-    manifest.write_to_file(&mut file)
+    manifest.write_to_file(&mut file)?;
+    add_extern_crates(args, &manifest, &Manifest::find_path(&manifest_path)?, deps)?;
+
+    warn_duplicates(&manifest, &section, deps);
+    for (dep, features) in deps.iter().zip(&features) {
+        if let Some(ref features) = *features {
+            print_features(dep, features);
+        }
+    }
+
+    Ok(())
 }
 
 fn main() {
@@ -1,14 +1,29 @@
 //! Handle `cargo rm` arguments
 
+use cargo_edit::validate_target;
+use std::error::Error;
+
 #[derive(Debug, Deserialize)]
 /// Docopts input args.
 pub struct Args {
-    /// Crate name
-    pub arg_crate: String,
+    /// Crate names
+    pub arg_crates: Vec<String>,
     /// dev-dependency
     pub flag_dev: bool,
     /// build-dependency
     pub flag_build: bool,
+    /// Target platform
+    pub flag_target: Option<String>,
+    /// `--all-sections`
+    pub flag_all_sections: bool,
+    /// `--keep-empty-features`
+    pub flag_keep_empty_features: bool,
+    /// `--unused`
+    pub flag_unused: bool,
+    /// `--remove`
+    pub flag_remove: bool,
+    /// `--extern`
+    pub flag_extern: bool,
     /// `Cargo.toml` path
     pub flag_manifest_path: Option<String>,
     /// `--version`
@@ -17,13 +32,24 @@ pub struct Args {
 
 impl Args {
     /// Get depenency section
-    pub fn get_section(&self) -> &'static str {
-        if self.flag_dev {
+    pub fn get_section(&self) -> Result<Vec<String>, Box<Error>> {
+        let kind = if self.flag_dev {
             "dev-dependencies"
         } else if self.flag_build {
             "build-dependencies"
         } else {
             "dependencies"
+        };
+
+        if let Some(ref target) = self.flag_target {
+            validate_target(target)?;
+            Ok(vec![
+                "target".to_owned(),
+                target.trim().to_owned(),
+                kind.to_owned(),
+            ])
+        } else {
+            Ok(vec![kind.to_owned()])
         }
     }
 }
@@ -31,9 +57,15 @@ impl Args {
 impl Default for Args {
     fn default() -> Args {
         Args {
-            arg_crate: "demo".to_owned(),
+            arg_crates: vec!["demo".to_owned()],
             flag_dev: false,
             flag_build: false,
+            flag_target: None,
+            flag_all_sections: false,
+            flag_keep_empty_features: false,
+            flag_unused: false,
+            flag_remove: false,
+            flag_extern: false,
             flag_manifest_path: None,
             flag_version: false,
         }
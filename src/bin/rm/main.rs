@@ -12,41 +12,196 @@ extern crate serde_derive;
 
 use std::error::Error;
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
 
 extern crate cargo_edit;
-use cargo_edit::Manifest;
+use cargo_edit::{find_crate_roots, find_unused_dependencies, format_table_path,
+                 remove_extern_crate_from_file, FeatureChange, Manifest, Usage};
 
 mod args;
 use args::Args;
 
 static USAGE: &'static str = r"
 Usage:
-    cargo rm <crate> [--dev|--build] [options]
+    cargo rm <crates>... [--dev|--build] [options]
+    cargo rm --unused [--remove] [options]
     cargo rm (-h|--help)
     cargo rm --version
 
 Options:
     -D --dev                Remove crate as development dependency.
     -B --build              Remove crate as build dependency.
+    --target <target>       Remove crate as dependency of the given target platform, e.g.
+                            `cfg(unix)`. Combine with `--dev` or `--build` for target-specific
+                            development or build dependencies.
+    --all-sections          Remove crate from every section it is in, including target-specific
+                            ones.
+    --keep-empty-features   Keep features that are left empty after references to the removed
+                            crates were removed from them.
+    --unused                List the dependencies that are never referenced by the crate's
+                            sources.
+    --remove                Remove the unused dependencies instead of only listing them.
+    --extern                Also remove the `extern crate` declarations of crates that are no
+                            longer a dependency in any section from `src/lib.rs` and
+                            `src/main.rs`.
     --manifest-path=<path>  Path to the manifest to remove a dependency from.
     -h --help               Show this help page.
     -V --version            Show version.
 
-Remove a dependency from a Cargo.toml manifest file.
+Remove one or more dependencies from a Cargo.toml manifest file.
+
+Once a crate is no longer a dependency in any section, references to it (`foo`, `dep:foo`,
+`foo/bar` and `foo?/bar`) are removed from `[features]`.
+
+With `--unused`, the sources in `src/`, `tests/`, `benches/` and `examples/` and the build script
+are searched for `extern crate`, `use` and path references (`foo::`) to each dependency. This is a
+static check: dependencies that are only needed for their side effects, e.g. for linking, are
+reported as unused, so review the list before removing them with `--remove`. Normal dependencies
+that are only used by tests or the build script are reported, but never removed. Source files that
+are not valid UTF-8 are skipped with a warning.
 ";
 
+/// Get the sections to remove a crate from.
+fn get_sections(
+    args: &Args,
+    manifest: &Manifest,
+    name: &str,
+) -> Result<Vec<Vec<String>>, Box<Error>> {
+    if !args.flag_all_sections {
+        return Ok(vec![args.get_section()?]);
+    }
+
+    let sections = manifest.find_dependency_sections(name);
+    if sections.is_empty() {
+        Err(From::from(format!(
+            "The dependency `{}` could not be found in any section.",
+            name
+        )))
+    } else {
+        Ok(sections)
+    }
+}
+
+/// Remove a crate from a section, and from `[features]` (and the crate roots, with `--extern`)
+/// once it is in no section anymore.
+fn remove_dependency(
+    args: &Args,
+    manifest: &mut Manifest,
+    manifest_path: &Path,
+    section: &[String],
+    name: &str,
+) -> Result<(), Box<Error>> {
+    manifest.remove_from_table(section, name)?;
+    println!("Removed `{}` from `{}`", name, format_table_path(section));
+
+    if manifest.find_dependency_sections(name).is_empty() {
+        if args.flag_extern {
+            let crate_root = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+            for root in find_crate_roots(manifest, crate_root) {
+                if remove_extern_crate_from_file(&root, name)? {
+                    println!(
+                        "Removed `extern crate {};` from `{}`",
+                        name.replace('-', "_"),
+                        root.display()
+                    );
+                }
+            }
+        }
+
+        for change in manifest.remove_from_features(name, !args.flag_keep_empty_features) {
+            match change {
+                FeatureChange::RemovedReference { feature, value } => {
+                    println!("Removed `{}` from feature `{}`", value, feature)
+                }
+                FeatureChange::RemovedFeature(feature) => {
+                    println!("Removed feature `{}`, which was left empty", feature)
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// List (or remove, with `--remove`) the dependencies the crate's sources never reference.
+fn remove_unused(
+    args: &Args,
+    manifest: &mut Manifest,
+    manifest_path: &Path,
+) -> Result<(), Box<Error>> {
+    let crate_root = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let unused = find_unused_dependencies(manifest, crate_root)?;
+
+    if unused.is_empty() {
+        println!("No unused dependencies found");
+    }
+
+    for dependency in unused {
+        let section = format_table_path(&dependency.section);
+        match dependency.usage {
+            Usage::Unused if !args.flag_remove => {
+                println!("`{}` in `{}` is unused", dependency.name, section)
+            }
+            Usage::Unused => remove_dependency(
+                args,
+                manifest,
+                manifest_path,
+                &dependency.section,
+                &dependency.name,
+            )?,
+            Usage::OnlyDev => println!(
+                "WARN: `{}` in `{}` is only used by tests, benches or examples. Consider making it \
+                 a development dependency.",
+                dependency.name,
+                section
+            ),
+            Usage::OnlyBuild => println!(
+                "WARN: `{}` in `{}` is only used by the build script. Consider making it a build \
+                 dependency.",
+                dependency.name,
+                section
+            ),
+        }
+    }
+
+    Ok(())
+}
+
 fn handle_rm(args: &Args) -> Result<(), Box<Error>> {
     let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
     let mut manifest = Manifest::open(&manifest_path)?;
 
-    manifest
-        .remove_from_table(args.get_section(), args.arg_crate.as_ref())
-        .map_err(From::from)
-        .and_then(|_| {
-            let mut file = Manifest::find_file(&manifest_path)?;
-            manifest.write_to_file(&mut file)
-        })
+    if (args.flag_all_sections || args.flag_unused) &&
+        (args.flag_dev || args.flag_build || args.flag_target.is_some())
+    {
+        return Err(From::from(
+            "`--all-sections` and `--unused` cannot be combined with `--dev`, `--build` or \
+             `--target`.",
+        ));
+    }
+
+    if args.flag_remove && !args.flag_unused {
+        return Err(From::from("`--remove` can only be used with `--unused`."));
+    }
+
+    let path = Manifest::find_path(&manifest_path)?;
+    if args.flag_unused {
+        remove_unused(args, &mut manifest, &path)?;
+    } else {
+        for name in &args.arg_crates {
+            for section in get_sections(args, &manifest, name)? {
+                remove_dependency(args, &mut manifest, &path, &section, name)?;
+            }
+        }
+    }
+
+    if args.flag_unused && !args.flag_remove {
+        return Ok(());
+    }
+
+    let mut file = Manifest::find_file(&manifest_path)?;
+    manifest.write_to_file(&mut file)
 }
 
 fn main() {
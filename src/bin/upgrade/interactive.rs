@@ -0,0 +1,129 @@
+//! Ask which upgrades to make with `--interactive`
+
+use cargo_edit::{classify_change, format_table_path};
+use semver::Version;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// What to do with a proposed upgrade
+#[derive(Debug, PartialEq)]
+pub enum Answer {
+    /// Make the upgrade
+    Accept,
+    /// Leave the dependency as it is
+    Skip,
+    /// Upgrade to another version instead
+    Version(Version),
+}
+
+/// Parse an answer to a prompt, `None` if it makes no sense.
+///
+/// An empty answer accepts the upgrade.
+fn parse_answer(answer: &str) -> Option<Answer> {
+    match answer.trim() {
+        "" | "y" | "Y" | "yes" => Some(Answer::Accept),
+        "n" | "N" | "no" => Some(Answer::Skip),
+        other => Version::parse(other.trim_left_matches('v'))
+            .ok()
+            .map(Answer::Version),
+    }
+}
+
+/// Asks the user about every proposed upgrade, reading the answers line by line
+pub struct Prompt {
+    input: Box<BufRead>,
+}
+
+impl Prompt {
+    /// Read the answers from the given input, usually stdin.
+    pub fn new(input: Box<BufRead>) -> Prompt {
+        Prompt { input: input }
+    }
+
+    /// Ask whether to upgrade a dependency from `old` to `new`, until a valid answer is given.
+    ///
+    /// Other versions can only be picked if `allow_version` is set. The end of the input skips all
+    /// remaining upgrades.
+    pub fn ask(
+        &mut self,
+        section: &[String],
+        name: &str,
+        old: &str,
+        new: &str,
+        allow_version: bool,
+    ) -> Result<Answer, Box<Error>> {
+        let choices = if allow_version {
+            "[Y/n/<version>]"
+        } else {
+            "[Y/n]"
+        };
+
+        loop {
+            write!(
+                io::stderr(),
+                "{}: {} {} -> {} {}. Upgrade? {} ",
+                format_table_path(section),
+                name,
+                old,
+                new,
+                classify_change(old, new),
+                choices
+            )?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                writeln!(io::stderr())?;
+                return Ok(Answer::Skip);
+            }
+
+            match parse_answer(&line) {
+                Some(Answer::Version(_)) if !allow_version => {}
+                Some(answer) => return Ok(answer),
+                None => {}
+            }
+            writeln!(
+                io::stderr(),
+                "Please answer `y`, `n`{}.",
+                if allow_version { " or a version" } else { "" }
+            )?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_answers() {
+        assert_eq!(parse_answer("\n"), Some(Answer::Accept));
+        assert_eq!(parse_answer("y\n"), Some(Answer::Accept));
+        assert_eq!(parse_answer("no\n"), Some(Answer::Skip));
+        assert_eq!(
+            parse_answer("v1.2.3\n"),
+            Some(Answer::Version(Version::parse("1.2.3").unwrap()))
+        );
+        assert_eq!(parse_answer("1.2\n"), None);
+        assert_eq!(parse_answer("maybe\n"), None);
+    }
+
+    #[test]
+    fn asks_until_answered() {
+        let mut prompt = Prompt::new(Box::new(&b"maybe\n2.0.0\nn\n2.0.0\n"[..]));
+        let section = vec!["dependencies".to_owned()];
+
+        assert_eq!(
+            prompt.ask(&section, "foo", "1.0", "1.4", false).unwrap(),
+            Answer::Skip
+        );
+        assert_eq!(
+            prompt.ask(&section, "foo", "1.0", "1.4", true).unwrap(),
+            Answer::Version(Version::parse("2.0.0").unwrap())
+        );
+        // The end of the input
+        assert_eq!(
+            prompt.ask(&section, "foo", "1.0", "1.4", true).unwrap(),
+            Answer::Skip
+        );
+    }
+}
@@ -6,76 +6,976 @@
 
 extern crate docopt;
 extern crate pad;
+extern crate semver;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate toml;
 
+use semver::{Version, VersionReq};
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
 
 extern crate cargo_edit;
-use cargo_edit::{Manifest, get_latest_dependency};
+use cargo_edit::{find_branch_head, find_latest_tag, is_compatible, is_pinned, list_remote_refs,
+                 lowest_version, parse_replace_key, read_advisories, select_locked_version,
+                 upgrade_requirement, Advisory, ChangeKind, Dependency, Manifest,
+                 PublishedBefore, get_compatible_dependency, get_crate_version_from_path,
+                 get_latest_dependency, get_minimal_dependency};
 
-static USAGE: &'static str = r"
+mod interactive;
+use interactive::{Answer, Prompt};
+mod policy;
+use policy::Policy;
+mod report;
+use report::Report;
+
+static USAGE: &'static str = r#"
 Upgrade all dependencies in a manifest file to the latest version.
 
 Usage:
-    cargo upgrade [--dependency <dep>...] [--manifest-path <path>]
+    cargo upgrade [--dependency <dep>...] [--exclude <dep>...] [--precise <version>...] [options]
     cargo upgrade (-h | --help)
     cargo upgrade (-V | --version)
 
 Options:
     -d --dependency <dep>       Specific dependency to upgrade. If this option is used, only the
                                 specified dependencies will be upgraded.
+    --exclude <dep>             Dependency not to upgrade. Dependencies are matched by their name
+                                in the manifest or their package name.
+    --precise <version>         Set an exact requirement (`=1.4.2`) for the given dependencies
+                                instead, in every section they appear in. Either a version for
+                                the dependencies given with `--dependency`, or `<dep>@<version>`.
+                                The version has to be published and not yanked.
     --manifest-path <path>      Path to the manifest to upgrade.
+    --before <date>             Only upgrade to versions published on crates.io before the given
+                                date (`YYYY-MM-DD`, UTC).
+    --compatible                Only upgrade within the semver compatible range of the current
+                                requirements, e.g. from `1.2` to `1.4`, but not to `2.0`.
+    --incompatible              Only upgrade requirements whose latest version is not semver
+                                compatible with them, i.e. major bumps (or minor ones for `0.x`).
+    --to-lockfile               Upgrade requirements to the versions resolved in `Cargo.lock`
+                                instead of the ones on crates.io. Works offline.
+    --advisories <path>         Only raise the requirements whose versions in `Cargo.lock` are
+                                affected by an advisory in a checkout of the RustSec advisory
+                                database at the given path, to the smallest patched version.
+    --minimal                   Raise the lower bound of requirements to the oldest version
+                                they allow that is not yanked instead, e.g. `1.0` to `1.0.2` if
+                                `1.0.0` and `1.0.1` were yanked, so that building with the
+                                minimal versions works. Requirements are never lowered.
+    --git                       Also upgrade git dependencies: move `tag` to the highest semver
+                                tag of the repository, and `rev` to the head of the tracked
+                                branch (or `HEAD`).
+    --allow-prerelease          Also upgrade to prerelease versions, and git tags. Dependencies
+                                on a prerelease are always upgraded to the newest prerelease or
+                                release of the same series, e.g. from `2.0.0-beta.3` to `2.0.0`.
+    --pinned                    Also upgrade pinned requirements (`=1.2.3`), which are skipped by
+                                default.
+    --check                     Only report the available upgrades, without changing the manifest.
+                                Exits with 2 if there are any, and 0 if everything is current.
+    --fail-on <kind>            With `--check`, only exit with 2 for upgrades of at least the
+                                given kind: `major`, `minor` or `patch`.
+    -i --interactive            Ask for every upgrade whether to make it, skip it, or upgrade to
+                                another version instead.
+    --format <format>           How to report the upgraded dependencies, `text` or `json`.
+                                [default: text]
     -h --help                   Show this help page.
     -V --version                Show version.
 
-Dev, build, and all target dependencies will also be upgraded. Only dependencies from crates.io are
-supported. Git/path dependencies will be ignored.
-";
+Dev, build, and all target dependencies will also be upgraded, as well as the ones in
+`[workspace.dependencies]` and `[patch.*]`. Dependencies inherited from the workspace are left as
+they are.
+
+Only dependencies from crates.io are upgraded by default. Git dependencies are upgraded with
+`--git`. Path dependencies that also have a `version` are synced with the `package.version` of the
+crate they point to, other path dependencies are ignored. This happens in the manifests of all
+other workspace members too, but not with `--minimal`. The same goes for the versions in
+`[replace]` keys (`foo:1.2.3`), which Cargo requires to match the replacing crate: they are synced
+with the crate a path replacement points to, and left as they are for git replacements.
+
+The operator (`=`, `~`, `^` or `>=`) and the number of version components of a requirement are
+kept, e.g. `~1.2` becomes `~1.4`, and `1.0` stays `1.0` if `1.0.117` is the latest version.
+Requirements with an upper bound or several comparators are left as they are.
+
+With `--to-lockfile`, a crate that is resolved to several versions is upgraded to the newest one
+matching its requirement, or else the newest semver compatible one. Versions beyond the limits of
+the policy below are not picked.
+
+Crates can also be excluded, or limited, permanently in `[package.metadata.cargo-upgrade]` or
+`[workspace.metadata.cargo-upgrade]`, also of the workspace root. Keys in the package's metadata
+take precedence:
+
+    ignore = ["openssl"]       # never upgraded
+    patch-only = ["serde"]     # only upgraded to newer patch versions
+    max = { tokio = "<2" }     # never upgraded beyond the given requirement
+
+Advisories for crates that are only transitive dependencies, or that have no patched version, are
+reported as unfixed.
+
+Every upgraded dependency is reported by section, with its old and new version requirement and
+whether the change is a `major`, `minor` or `patch` one, or `unchanged`.
+
+Exit status: 0 on success, 1 on errors, and 2 with `--check` if upgrades are available (or
+advisories are unfixed).
+"#;
 
 /// Docopts input args.
 #[derive(Debug, Deserialize)]
 struct Args {
     /// `--dependency -d <dep>`
     flag_dependency: Vec<String>,
+    /// `--exclude <dep>`
+    flag_exclude: Vec<String>,
+    /// `--precise <version>`
+    flag_precise: Vec<String>,
     /// `--manifest-path <path>`
     flag_manifest_path: Option<String>,
+    /// `--before <date>`
+    flag_before: Option<String>,
+    /// `--compatible`
+    flag_compatible: bool,
+    /// `--incompatible`
+    flag_incompatible: bool,
+    /// `--to-lockfile`
+    flag_to_lockfile: bool,
+    /// `--advisories <path>`
+    flag_advisories: Option<String>,
+    /// `--minimal`
+    flag_minimal: bool,
+    /// `--git`
+    flag_git: bool,
+    /// `--allow-prerelease`
+    flag_allow_prerelease: bool,
+    /// `--pinned`
+    flag_pinned: bool,
+    /// `--check`
+    flag_check: bool,
+    /// `--fail-on <kind>`
+    flag_fail_on: Option<String>,
+    /// `--interactive`
+    flag_interactive: bool,
+    /// `--format <format>`
+    flag_format: String,
     /// `--version`
     flag_version: bool,
 }
 
-fn is_version_dependency(dep: &toml::Value) -> bool {
-    if let Some(table) = dep.as_table() {
-        !table.contains_key("git") && !table.contains_key("path")
+/// The version requirement of a dependency from crates.io, `None` for git/path dependencies and
+/// the ones inherited from the workspace
+fn get_version_requirement(dep: &toml::Value) -> Option<String> {
+    match *dep {
+        toml::Value::String(ref version) => Some(version.clone()),
+        toml::Value::Table(ref table) if !table.contains_key("git") &&
+            !table.contains_key("path") &&
+            !table.contains_key("workspace") =>
+        {
+            Some(
+                table
+                    .get("version")
+                    .and_then(toml::Value::as_str)
+                    .unwrap_or("*")
+                    .to_owned(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Find the version to upgrade a dependency to, `None` if it should be left as it is.
+///
+/// The version is looked up by the `package` name, in `Cargo.lock` if one is given or else on the
+/// registry. Either way, it is kept within the limits of `--compatible` and the policy.
+fn find_upgrade(
+    args: &Args,
+    name: &str,
+    package: &str,
+    old_version: &str,
+    before: Option<&PublishedBefore>,
+    lock_file: Option<&Manifest>,
+    policy: &Policy,
+) -> Result<Option<String>, Box<Error>> {
+    let lowest = lowest_version(old_version);
+    // Dependencies on a prerelease follow its series, to newer prereleases or releases.
+    let on_prerelease = lowest.as_ref().map_or(false, |lowest| lowest.is_prerelease());
+
+    let mut limits = vec![];
+    let patch_only = policy.is_patch_only(name, package);
+    if args.flag_compatible || patch_only || on_prerelease {
+        // Without a lowest version, there is no range to stay in.
+        let lowest = match lowest {
+            Some(ref lowest) => lowest.to_string(),
+            None => return Ok(None),
+        };
+        if args.flag_compatible || on_prerelease {
+            limits.push(lowest.clone());
+        }
+        if patch_only {
+            limits.push(format!("~{}", lowest));
+        }
+    }
+    if let Some(max) = policy.max(name, package) {
+        limits.push(max.to_owned());
+    }
+
+    let allow_prerelease = args.flag_allow_prerelease || on_prerelease;
+    let latest = if let Some(lock_file) = lock_file {
+        let limits = limits
+            .iter()
+            .map(|limit| VersionReq::parse(limit))
+            .collect::<Result<Vec<_>, _>>()?;
+        let locked: Vec<Version> = lock_file
+            .get_locked_versions(package)
+            .into_iter()
+            .filter(|version| limits.iter().all(|limit| limit.matches(version)))
+            .collect();
+        match select_locked_version(old_version, &locked) {
+            Some(version) => version.to_string(),
+            None => return Ok(None),
+        }
+    } else if limits.is_empty() {
+        let latest = get_latest_dependency(package, allow_prerelease, before)?;
+        latest.version().unwrap_or("").to_owned()
     } else {
-        true
+        let latest =
+            get_compatible_dependency(package, &limits.join(", "), allow_prerelease, before)?;
+        latest.version().unwrap_or("").to_owned()
+    };
+
+    // Never "upgrade" to an older version.
+    if let (Some(lowest), Some(latest)) = (lowest, lowest_version(&latest)) {
+        if latest < lowest {
+            return Ok(None);
+        }
+    }
+    if args.flag_incompatible && is_compatible(old_version, &latest) {
+        return Ok(None);
+    }
+
+    Ok(Some(latest))
+}
+
+/// Raise the lower bound of a requirement to a version, like a patched or the oldest available
+/// one, keeping its operator, e.g. `~1.2` and `1.2.5` give `~1.2.5`. Requirements that already
+/// exclude all older versions are left as they are. `None` if the requirement cannot be raised,
+/// see `upgrade_requirement`.
+fn raise_requirement(old: &str, version: &Version) -> Option<String> {
+    if lowest_version(old).map_or(false, |lowest| lowest >= *version) {
+        return Some(old.to_owned());
+    }
+
+    upgrade_requirement(old, &version.to_string()).map(|new| {
+        if lowest_version(&new).map_or(false, |lowest| lowest >= *version) {
+            new
+        } else {
+            // Fewer version components would still allow older versions.
+            let operator: String = new.trim()
+                .chars()
+                .take_while(|c| "=^~>".contains(*c))
+                .collect();
+            format!("{}{}", operator, version)
+        }
+    })
+}
+
+/// Raise a requirement past all advisories affecting the versions locked for it, to the smallest
+/// patched versions. Advisories that cannot be fixed that way are reported, and the locked
+/// versions that were looked at are added to `covered`.
+fn fix_advisories(
+    package: &str,
+    old_version: &str,
+    lock_file: &Manifest,
+    advisories: &[Advisory],
+    covered: &mut BTreeSet<(String, Version)>,
+    report: &mut Report,
+) -> String {
+    let req = VersionReq::parse(old_version).ok();
+    let mut new_version = old_version.to_owned();
+
+    for locked in lock_file.get_locked_versions(package) {
+        if req.as_ref().map_or(false, |req| !req.matches(&locked)) {
+            continue;
+        }
+        covered.insert((package.to_owned(), locked.clone()));
+
+        let affecting = advisories
+            .iter()
+            .filter(|advisory| advisory.package == package && advisory.affects(&locked));
+        for advisory in affecting {
+            let raised = match advisory.first_patched_version(&locked) {
+                Some(patched) => raise_requirement(&new_version, &patched),
+                None => {
+                    report.add_unfixed(
+                        &advisory.id,
+                        package,
+                        &locked.to_string(),
+                        "no patched version",
+                    );
+                    continue;
+                }
+            };
+            match raised {
+                Some(raised) => new_version = raised,
+                None => report.add_unfixed(
+                    &advisory.id,
+                    package,
+                    &locked.to_string(),
+                    &format!("`{}` cannot be raised", old_version),
+                ),
+            }
+        }
     }
+
+    new_version
+}
+
+/// Read the `--precise` versions by dependency, and check that they are available on the
+/// registry. Dependencies can be given by their name in the manifest or their package name.
+fn find_precise_versions(
+    args: &Args,
+    manifest: &Manifest,
+) -> Result<BTreeMap<String, String>, Box<Error>> {
+    let mut precise = BTreeMap::new();
+
+    for value in &args.flag_precise {
+        let mut parts = value.splitn(2, '@');
+        let (names, version) = match (parts.next(), parts.next()) {
+            (Some(name), Some(version)) => (vec![name.to_owned()], version),
+            _ if args.flag_dependency.is_empty() => {
+                return Err(From::from(format!(
+                    "`--precise {}` needs the dependencies to set it for, use `--dependency \
+                     <dep>` or `<dep>@{}`.",
+                    value,
+                    value
+                )))
+            }
+            _ => (args.flag_dependency.clone(), value.as_str()),
+        };
+        Version::parse(version)
+            .map_err(|err| format!("Invalid version `{}` for `--precise`: {}", version, err))?;
+
+        for name in names {
+            let package = manifest
+                .get_all_sections()
+                .into_iter()
+                .filter(|&(ref table_path, _)| *table_path != ["replace"])
+                .flat_map(|(_, table)| table.into_iter())
+                .filter_map(|(key, dependency)| {
+                    let package = dependency
+                        .get("package")
+                        .and_then(toml::Value::as_str)
+                        .unwrap_or(&key)
+                        .to_owned();
+                    if key == name || package == name {
+                        Some(package)
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .ok_or_else(|| format!("The dependency `{}` could not be found.", name))?;
+
+            get_compatible_dependency(&package, &format!("={}", version), false, None)?;
+            precise.insert(name, version.to_owned());
+        }
+    }
+
+    Ok(precise)
 }
 
-fn update_manifest(
-    manifest_path: &Option<String>,
-    only_update: &[String],
+/// Set a key of a dependency that is written as a table, keeping all others.
+fn set_entry_key(
+    manifest: &mut Manifest,
+    table_path: &[String],
+    name: &str,
+    key: &str,
+    value: &str,
 ) -> Result<(), Box<Error>> {
-    let manifest_path = manifest_path.as_ref().map(From::from);
+    let entry = manifest
+        .get_table(table_path)?
+        .get_mut(name)
+        .and_then(toml::Value::as_table_mut);
+    if let Some(entry) = entry {
+        entry.insert(key.to_owned(), toml::Value::String(value.to_owned()));
+    }
+
+    Ok(())
+}
+
+/// Move the version of a `[replace]` key like `foo:1.2.3` to a new one. The replacement itself is
+/// left as it is.
+fn set_replace_key_version(
+    manifest: &mut Manifest,
+    key: &str,
+    old_version: &str,
+    new_version: &str,
+) -> Result<(), Box<Error>> {
+    if new_version != old_version {
+        let prefix = &key[..key.len() - old_version.len()];
+        let table = manifest.get_table(&["replace".to_owned()])?;
+        if let Some(replacement) = table.remove(key) {
+            table.insert(format!("{}{}", prefix, new_version), replacement);
+        }
+    }
+
+    Ok(())
+}
+
+/// Where a version picked at the `--interactive` prompt is looked up
+struct PickedVersions<'a> {
+    /// The package name of the dependency
+    package: &'a str,
+    /// Whether prereleases can be picked, as with `--allow-prerelease`
+    allow_prerelease: bool,
+    /// The date given with `--before`
+    before: Option<&'a PublishedBefore>,
+}
+
+/// With `--interactive`, ask whether to make an upgrade. Returns what to write, which is `old` if
+/// the upgrade is skipped.
+///
+/// If `picked` is given, another version can be picked as well. It is checked against the
+/// registry like the upgrades themselves are, and written the way `old` is, e.g. `1.3.0` for
+/// `^1.0` gives `^1.3`.
+fn confirm_upgrade(
+    prompt: &mut Option<Prompt>,
+    section: &[String],
+    name: &str,
+    old: &str,
+    new: String,
+    picked: Option<&PickedVersions>,
+) -> Result<String, Box<Error>> {
+    let prompt = match *prompt {
+        Some(ref mut prompt) if new != old => prompt,
+        _ => return Ok(new),
+    };
+
+    loop {
+        match prompt.ask(section, name, old, &new, picked.is_some())? {
+            Answer::Accept => return Ok(new),
+            Answer::Skip => return Ok(old.to_owned()),
+            Answer::Version(version) => {
+                let picked = match picked {
+                    Some(picked) => picked,
+                    None => continue,
+                };
+                let version = version.to_string();
+                let found = get_compatible_dependency(
+                    picked.package,
+                    &format!("={}", version),
+                    picked.allow_prerelease,
+                    picked.before,
+                );
+                match found {
+                    Ok(_) => {
+                        return Ok(upgrade_requirement(old, &version).unwrap_or(version));
+                    }
+                    Err(err) => writeln!(io::stderr(), "{}", err)?,
+                }
+            }
+        }
+    }
+}
+
+/// Move the `tag` of a git dependency to the highest semver tag of its repository, or its `rev`
+/// to the head of the branch it tracks. Dependencies that follow a branch are left as they are.
+fn upgrade_git_dependency(
+    manifest: &mut Manifest,
+    table_path: &[String],
+    name: &str,
+    dependency: &toml::Value,
+    allow_prerelease: bool,
+    prompt: &mut Option<Prompt>,
+    report: &mut Report,
+) -> Result<(), Box<Error>> {
+    let get = |key| dependency.get(key).and_then(toml::Value::as_str);
+    let (key, old_ref) = match (get("tag"), get("rev")) {
+        (Some(tag), _) => ("tag", tag),
+        (None, Some(rev)) => ("rev", rev),
+        _ => return Ok(()),
+    };
+    let refs = list_remote_refs(get("git").unwrap_or(""))?;
+
+    let new_ref = if key == "tag" {
+        let old_version = lowest_version(old_ref);
+        let allow_prerelease =
+            allow_prerelease || old_version.as_ref().map_or(false, |v| v.is_prerelease());
+        find_latest_tag(&refs, allow_prerelease).and_then(|tag| {
+            let newer = match (old_version, lowest_version(&tag)) {
+                (Some(old), Some(new)) => new > old,
+                _ => true,
+            };
+            if newer {
+                Some(tag)
+            } else {
+                None
+            }
+        })
+    } else {
+        // Keep abbreviated commit ids abbreviated.
+        find_branch_head(&refs, get("branch")).map(|id| {
+            let length = cmp::min(cmp::max(old_ref.len(), 7), id.len());
+            id[..length].to_owned()
+        })
+    };
+    let new_ref = new_ref.unwrap_or_else(|| old_ref.to_owned());
+    let new_ref = confirm_upgrade(prompt, table_path, name, old_ref, new_ref, None)?;
+
+    set_entry_key(manifest, table_path, name, key, &new_ref)?;
+    report.add(table_path, name, old_ref, &new_ref);
+
+    Ok(())
+}
+
+/// Read the `package.version` of the crate at `path`, which `name` depends on. Warns and returns
+/// `None` if it cannot be read.
+fn read_path_version(name: &str, path: &Path) -> Result<Option<String>, Box<Error>> {
+    match get_crate_version_from_path(&path.to_string_lossy()) {
+        Ok(version) => Ok(Some(version)),
+        Err(_) => {
+            writeln!(
+                io::stderr(),
+                "WARN: Could not read the version of `{}` from `{}`, leaving it as it is.",
+                name,
+                path.join("Cargo.toml").display()
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+/// Sync the `version` of a path dependency with the version of the crate it points to.
+///
+/// `member` is the path of the manifest for the report, if it is another workspace member than
+/// the one being upgraded.
+fn sync_path_dependency(
+    manifest: &mut Manifest,
+    manifest_dir: &Path,
+    member: Option<&str>,
+    table_path: &[String],
+    name: &str,
+    dependency: &toml::Value,
+    prompt: &mut Option<Prompt>,
+    report: &mut Report,
+) -> Result<(), Box<Error>> {
+    let get = |key| dependency.get(key).and_then(toml::Value::as_str);
+    let (path, old_version) = match (get("path"), get("version")) {
+        (Some(path), Some(version)) => (manifest_dir.join(path), version),
+        _ => return Ok(()),
+    };
+
+    let local_version = match read_path_version(name, &path)? {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+    let new_version =
+        upgrade_requirement(old_version, &local_version).unwrap_or_else(|| old_version.to_owned());
+    let new_version = confirm_upgrade(prompt, table_path, name, old_version, new_version, None)?;
+
+    set_entry_key(manifest, table_path, name, "version", &new_version)?;
+    report.add_in(member, table_path, name, old_version, &new_version);
+
+    Ok(())
+}
+
+/// Sync the path dependencies in the manifests of the other workspace members, see
+/// `sync_path_dependency`.
+fn sync_workspace_members(
+    args: &Args,
+    manifest_dir: &Path,
+    policy: &Policy,
+    prompt: &mut Option<Prompt>,
+    report: &mut Report,
+) -> Result<(), Box<Error>> {
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
+    let manifest_dir = manifest_dir.canonicalize()?;
+
+    for member_path in Manifest::find_workspace_members(&manifest_path)? {
+        let mut member = Manifest::open(&Some(member_path.clone()))?;
+        let original = member.data.clone();
+        let member_dir = member_path.parent().unwrap_or_else(|| Path::new("."));
+        let label = member_path
+            .strip_prefix(&manifest_dir)
+            .unwrap_or(&member_path)
+            .display()
+            .to_string();
+
+        for (table_path, table) in member.get_all_sections() {
+            if table_path == ["replace"] {
+                continue;
+            }
+            for (name, dependency) in &table {
+                let package = dependency
+                    .get("package")
+                    .and_then(toml::Value::as_str)
+                    .unwrap_or(name);
+                if dependency.get("path").is_none() || is_skipped(args, policy, name, package) {
+                    continue;
+                }
+                sync_path_dependency(
+                    &mut member,
+                    member_dir,
+                    Some(&label),
+                    &table_path,
+                    name,
+                    dependency,
+                    prompt,
+                    report,
+                )?;
+            }
+        }
+
+        // Members that are left as they are are not rewritten.
+        if !args.flag_check && member.data != original {
+            let mut file = Manifest::find_file(&Some(member_path))?;
+            member.write_to_file(&mut file)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a dependency is left out by `--dependency`, `--exclude` or the policy. Dependencies
+/// are matched by their key and their package name.
+fn is_skipped(args: &Args, policy: &Policy, name: &str, package: &str) -> bool {
+    let matches = |dep: &String| dep == name || dep == package;
+    !args.flag_dependency.is_empty() && !args.flag_dependency.iter().any(&matches) ||
+        args.flag_exclude.iter().any(&matches) || policy.ignores(name, package)
+}
+
+/// Sync the version in a `[replace]` key like `foo:1.2.3` with the version of the crate a path
+/// replacement points to. Cargo requires them to match, so git replacements, whose version is not
+/// known without fetching them, are left as they are.
+fn sync_replace_key(
+    manifest: &mut Manifest,
+    manifest_dir: &Path,
+    key: &str,
+    replacement: &toml::Value,
+    prompt: &mut Option<Prompt>,
+    report: &mut Report,
+) -> Result<(), Box<Error>> {
+    let (name, old_version) = match parse_replace_key(key) {
+        Some(parsed) => parsed,
+        None => return Ok(()),
+    };
+    let path = match replacement.get("path").and_then(toml::Value::as_str) {
+        Some(path) => manifest_dir.join(path),
+        None => return Ok(()),
+    };
+
+    let local_version = match read_path_version(name, &path)? {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+    let table_path = ["replace".to_owned()];
+    let new_version =
+        confirm_upgrade(prompt, &table_path, name, old_version, local_version, None)?;
+
+    set_replace_key_version(manifest, key, old_version, &new_version)?;
+    report.add(&table_path, name, old_version, &new_version);
+
+    Ok(())
+}
+
+/// Upgrade the manifest, or with `--check` only look for upgrades. Returns whether `--check` found
+/// any upgrades that should fail the check.
+fn update_manifest(args: &Args) -> Result<bool, Box<Error>> {
+    if args.flag_compatible && args.flag_incompatible {
+        return Err(From::from(
+            "`--compatible` and `--incompatible` cannot be used together.",
+        ));
+    }
+    if args.flag_to_lockfile &&
+        (args.flag_compatible || args.flag_incompatible || args.flag_before.is_some() ||
+            args.flag_git)
+    {
+        return Err(From::from(
+            "`--to-lockfile` cannot be combined with `--compatible`, `--incompatible`, \
+             `--before` or `--git`.",
+        ));
+    }
+
+    if args.flag_advisories.is_some() &&
+        (args.flag_to_lockfile || args.flag_compatible || args.flag_incompatible ||
+            args.flag_before.is_some() || args.flag_git)
+    {
+        return Err(From::from(
+            "`--advisories` cannot be combined with `--to-lockfile`, `--compatible`, \
+             `--incompatible`, `--before` or `--git`.",
+        ));
+    }
+
+    if !args.flag_precise.is_empty() &&
+        (args.flag_to_lockfile || args.flag_compatible || args.flag_incompatible ||
+            args.flag_before.is_some() || args.flag_git || args.flag_advisories.is_some())
+    {
+        return Err(From::from(
+            "`--precise` cannot be combined with `--to-lockfile`, `--compatible`, \
+             `--incompatible`, `--before`, `--git` or `--advisories`.",
+        ));
+    }
+
+    if args.flag_minimal &&
+        (args.flag_to_lockfile || args.flag_compatible || args.flag_incompatible ||
+            args.flag_before.is_some() || args.flag_git || args.flag_advisories.is_some() ||
+            !args.flag_precise.is_empty())
+    {
+        return Err(From::from(
+            "`--minimal` cannot be combined with `--to-lockfile`, `--compatible`, \
+             `--incompatible`, `--before`, `--git`, `--advisories` or `--precise`.",
+        ));
+    }
+
+    if args.flag_interactive && args.flag_check {
+        return Err(From::from(
+            "`--interactive` cannot be used with `--check`.",
+        ));
+    }
+    if args.flag_fail_on.is_some() && !args.flag_check {
+        return Err(From::from("`--fail-on` can only be used with `--check`."));
+    }
+
+    let threshold = match args.flag_fail_on.as_ref().map(String::as_str) {
+        None | Some("patch") => ChangeKind::Patch,
+        Some("minor") => ChangeKind::Minor,
+        Some("major") => ChangeKind::Major,
+        Some(other) => {
+            return Err(From::from(format!(
+                "Unknown kind `{}` for `--fail-on`, expected `major`, `minor` or `patch`.",
+                other
+            )))
+        }
+    };
+    let json = match args.flag_format.as_ref() {
+        "text" => false,
+        "json" => true,
+        other => {
+            return Err(From::from(format!(
+                "Unknown format `{}`, expected `text` or `json`.",
+                other
+            )))
+        }
+    };
+
+    let manifest_path = args.flag_manifest_path.as_ref().map(From::from);
     let mut manifest = Manifest::open(&manifest_path).unwrap();
+    let before = match args.flag_before {
+        Some(ref date) => Some(date.parse::<PublishedBefore>()?),
+        None => None,
+    };
+    let lock_file = if args.flag_to_lockfile || args.flag_advisories.is_some() {
+        Some(Manifest::open_lock_file(&manifest_path)?)
+    } else {
+        None
+    };
+    let advisories = match args.flag_advisories {
+        Some(ref path) => Some(read_advisories(Path::new(path))?),
+        None => None,
+    };
+    let precise = find_precise_versions(args, &manifest)?;
+    // Crates that are direct dependencies, and the locked versions raised past their advisories
+    let mut direct = BTreeSet::new();
+    let mut covered = BTreeSet::new();
+    let manifest_file = Manifest::find_path(&manifest_path)?;
+    let manifest_dir = manifest_file.parent().unwrap_or_else(|| Path::new("."));
+    let workspace_root = Manifest::find_workspace_root(&manifest_path)?;
+    let policy = Policy::from_manifest(&manifest, workspace_root.as_ref().map(|root| &root.1))?;
+    let mut report = Report::default();
+    let mut prompt = if args.flag_interactive {
+        Some(Prompt::new(Box::new(io::BufReader::new(io::stdin()))))
+    } else {
+        None
+    };
+
+    for (table_path, table) in manifest.get_all_sections() {
+        for (key, old_value) in &table {
+            // `[replace]` keys are the name of a crate and the version that is replaced.
+            let replace = table_path == ["replace"];
+            let name = if replace {
+                match parse_replace_key(key) {
+                    Some((name, _)) => name,
+                    None => continue,
+                }
+            } else {
+                key.as_str()
+            };
+
+            let package = old_value
+                .get("package")
+                .and_then(toml::Value::as_str)
+                .unwrap_or(name);
+            if !replace {
+                direct.insert(package.to_owned());
+            }
+
+            if !precise.is_empty() {
+                let version = precise.get(name).or_else(|| precise.get(package));
+                let old_version = get_version_requirement(old_value);
+                if let (false, Some(version), Some(old_version)) = (replace, version, old_version) {
+                    let new_version = format!("={}", version);
+                    let dependency = Dependency::new(name).set_version(&new_version);
+                    manifest.update_table_entry(&table_path, &dependency)?;
+                    report.add(&table_path, name, &old_version, &new_version);
+                }
+                continue;
+            }
+
+            if is_skipped(args, &policy, name, package) {
+                continue;
+            }
+            // Only dependencies from the registry are in the advisory database.
+            if advisories.is_some() &&
+                (replace || old_value.get("git").is_some() || old_value.get("path").is_some())
+            {
+                continue;
+            }
 
-    for (table_path, table) in manifest.get_sections() {
-        for (name, old_value) in &table {
-            if (only_update.is_empty() || only_update.contains(name)) &&
-                is_version_dependency(old_value)
+            if replace {
+                sync_replace_key(
+                    &mut manifest,
+                    manifest_dir,
+                    key,
+                    old_value,
+                    &mut prompt,
+                    &mut report,
+                )?;
+                continue;
+            }
+            if args.flag_git && old_value.get("git").is_some() {
+                upgrade_git_dependency(
+                    &mut manifest,
+                    &table_path,
+                    name,
+                    old_value,
+                    args.flag_allow_prerelease,
+                    &mut prompt,
+                    &mut report,
+                )?;
+                continue;
+            }
+            // Only versions from the registry are lowered.
+            if old_value.get("path").is_some() && args.flag_minimal {
+                continue;
+            }
+            if old_value.get("path").is_some() {
+                sync_path_dependency(
+                    &mut manifest,
+                    manifest_dir,
+                    None,
+                    &table_path,
+                    name,
+                    old_value,
+                    &mut prompt,
+                    &mut report,
+                )?;
+                continue;
+            }
+
+            let old_version = match get_version_requirement(old_value) {
+                Some(version) => version,
+                None => continue,
+            };
+
+            let new_version = if let (Some(advisories), Some(lock_file)) =
+                (advisories.as_ref(), lock_file.as_ref())
             {
-                let latest_version = get_latest_dependency(name, false)?;
+                let new_version = fix_advisories(
+                    package,
+                    &old_version,
+                    lock_file,
+                    advisories,
+                    &mut covered,
+                    &mut report,
+                );
+                // Only the affected dependencies are reported.
+                if new_version == old_version {
+                    continue;
+                }
+                new_version
+            } else if args.flag_minimal {
+                let minimal =
+                    get_minimal_dependency(package, &old_version, args.flag_allow_prerelease)?;
+                minimal
+                    .version()
+                    .and_then(|version| Version::parse(version).ok())
+                    .and_then(|version| raise_requirement(&old_version, &version))
+                    .unwrap_or_else(|| old_version.clone())
+            } else {
+                if is_pinned(&old_version) && !args.flag_pinned {
+                    report.add(&table_path, name, &old_version, &old_version);
+                    continue;
+                }
+
+                let upgrade = find_upgrade(
+                    args,
+                    name,
+                    package,
+                    &old_version,
+                    before.as_ref(),
+                    lock_file.as_ref(),
+                    &policy,
+                )?;
+                upgrade
+                    .and_then(|latest| upgrade_requirement(&old_version, &latest))
+                    .unwrap_or_else(|| old_version.clone())
+            };
+            let picked = PickedVersions {
+                package: package,
+                allow_prerelease: args.flag_allow_prerelease,
+                before: before.as_ref(),
+            };
+            let new_version = confirm_upgrade(
+                &mut prompt,
+                &table_path,
+                name,
+                &old_version,
+                new_version,
+                Some(&picked),
+            )?;
+
+            let dependency = Dependency::new(name).set_version(&new_version);
+            manifest.update_table_entry(&table_path, &dependency)?;
+            report.add(&table_path, name, &old_version, &new_version);
+        }
+    }
+
+    if precise.is_empty() && advisories.is_none() && !args.flag_minimal {
+        sync_workspace_members(args, manifest_dir, &policy, &mut prompt, &mut report)?;
+    }
 
-                manifest.update_table_entry(&table_path, &latest_version)?;
+    if let (Some(advisories), Some(lock_file)) = (advisories.as_ref(), lock_file.as_ref()) {
+        for advisory in advisories {
+            for locked in lock_file.get_locked_versions(&advisory.package) {
+                if !advisory.affects(&locked) ||
+                    covered.contains(&(advisory.package.clone(), locked.clone()))
+                {
+                    continue;
+                }
+                let reason = if direct.contains(&advisory.package) {
+                    "no upgraded requirement matches it"
+                } else {
+                    "not a direct dependency"
+                };
+                report.add_unfixed(&advisory.id, &advisory.package, &locked.to_string(), reason);
             }
         }
     }
 
-    let mut file = Manifest::find_file(&manifest_path)?;
-    manifest.write_to_file(&mut file)
+    if !args.flag_check {
+        let mut file = Manifest::find_file(&manifest_path)?;
+        manifest.write_to_file(&mut file)?;
+    }
+
+    if json {
+        report.print_json()?;
+    } else {
+        report.print_text();
+    }
+
+    Ok(args.flag_check && (report.has_changes(threshold) || report.has_unfixed()))
 }
 
 fn main() {
@@ -88,12 +988,16 @@ fn main() {
         process::exit(0);
     }
 
-    if let Err(err) = update_manifest(&args.flag_manifest_path, &args.flag_dependency) {
-        writeln!(
-            io::stderr(),
-            "Command failed due to unhandled error: {}\n",
-            err
-        ).unwrap();
-        process::exit(1);
+    match update_manifest(&args) {
+        Ok(true) => process::exit(2),
+        Ok(false) => {}
+        Err(err) => {
+            writeln!(
+                io::stderr(),
+                "Command failed due to unhandled error: {}\n",
+                err
+            ).unwrap();
+            process::exit(1);
+        }
     }
 }
@@ -0,0 +1,159 @@
+//! Upgrade policy from the `cargo-upgrade` package or workspace metadata
+
+use cargo_edit::Manifest;
+use semver::VersionReq;
+use std::collections::BTreeMap;
+use std::error::Error;
+use toml;
+
+/// Which crates to upgrade, and how far
+///
+/// Crates are named by their package name or by their key in the dependency sections.
+///
+/// ```toml
+/// [package.metadata.cargo-upgrade]
+/// ignore = ["openssl"]
+/// patch-only = ["serde"]
+/// max = { tokio = "<2" }
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct Policy {
+    /// Crates that are never upgraded
+    pub ignore: Vec<String>,
+    /// Crates that are only upgraded to newer patch versions
+    pub patch_only: Vec<String>,
+    /// Version requirements crates are never upgraded beyond
+    pub max: BTreeMap<String, String>,
+}
+
+impl Policy {
+    /// Read the policy from the metadata of a manifest, and of the root of the workspace it
+    /// belongs to. Keys in the package's metadata take precedence.
+    pub fn from_manifest(
+        manifest: &Manifest,
+        workspace_root: Option<&Manifest>,
+    ) -> Result<Policy, Box<Error>> {
+        let metadata = manifest.get_tool_metadata_in_workspace(workspace_root, "cargo-upgrade");
+        let mut policy = Policy::default();
+
+        for (key, value) in &metadata {
+            match key.as_ref() {
+                "ignore" => policy.ignore = read_names(key, value)?,
+                "patch-only" => policy.patch_only = read_names(key, value)?,
+                "max" => {
+                    let caps = value.as_table().ok_or_else(|| invalid(key, "a table"))?;
+                    for (name, cap) in caps {
+                        let cap = cap.as_str()
+                            .ok_or_else(|| invalid(&format!("max.{}", name), "a string"))?;
+                        VersionReq::parse(cap).map_err(|err| {
+                            format!(
+                                "Invalid maximum `{}` for `{}` in the cargo-upgrade metadata: {}",
+                                cap,
+                                name,
+                                err
+                            )
+                        })?;
+                        policy.max.insert(name.clone(), cap.to_owned());
+                    }
+                }
+                _ => {
+                    return Err(From::from(format!(
+                        "Unknown key `{}` in the cargo-upgrade metadata, expected `ignore`, \
+                         `patch-only` or `max`.",
+                        key
+                    )))
+                }
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// Whether a dependency is never upgraded, by its key and package name
+    pub fn ignores(&self, name: &str, package: &str) -> bool {
+        self.ignore.iter().any(|ignored| ignored == name || ignored == package)
+    }
+
+    /// Whether a dependency is only upgraded to newer patch versions, by its key and package name
+    pub fn is_patch_only(&self, name: &str, package: &str) -> bool {
+        self.patch_only
+            .iter()
+            .any(|patch_only| patch_only == name || patch_only == package)
+    }
+
+    /// The version requirement a dependency is never upgraded beyond, if any, by its key and
+    /// package name
+    pub fn max(&self, name: &str, package: &str) -> Option<&str> {
+        self.max
+            .get(name)
+            .or_else(|| self.max.get(package))
+            .map(String::as_str)
+    }
+}
+
+fn read_names(key: &str, value: &toml::Value) -> Result<Vec<String>, Box<Error>> {
+    value
+        .as_array()
+        .and_then(|names| {
+            names
+                .iter()
+                .map(|name| name.as_str().map(ToOwned::to_owned))
+                .collect()
+        })
+        .ok_or_else(|| invalid(key, "a list of crate names"))
+}
+
+fn invalid(key: &str, expected: &str) -> Box<Error> {
+    From::from(format!(
+        "`{}` in the cargo-upgrade metadata must be {}.",
+        key,
+        expected
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(metadata: &str) -> Result<Policy, Box<Error>> {
+        let manifest: Manifest = format!(
+            "[package]\nname = \"foo\"\n[package.metadata.cargo-upgrade]\n{}",
+            metadata
+        ).parse()
+            .unwrap();
+        Policy::from_manifest(&manifest, None)
+    }
+
+    #[test]
+    fn reads_policy() {
+        let policy = policy(
+            r#"
+ignore = ["openssl"]
+patch-only = ["serde"]
+max = { tokio = "<2" }
+"#,
+        ).unwrap();
+
+        assert!(policy.ignores("openssl", "openssl"));
+        assert!(!policy.ignores("serde", "serde"));
+        assert!(policy.is_patch_only("serde", "serde"));
+        assert!(!policy.is_patch_only("tokio", "tokio"));
+        assert_eq!(policy.max("tokio", "tokio"), Some("<2"));
+        assert_eq!(policy.max("serde", "serde"), None);
+
+        // Renamed dependencies are matched by their package name too.
+        assert!(policy.ignores("ssl", "openssl"));
+        assert!(policy.is_patch_only("serde1", "serde"));
+        assert_eq!(policy.max("tokio01", "tokio"), Some("<2"));
+    }
+
+    #[test]
+    fn rejects_invalid_policy() {
+        assert_eq!(
+            policy("ignore = \"openssl\"").unwrap_err().to_string(),
+            "`ignore` in the cargo-upgrade metadata must be a list of crate names."
+        );
+        assert!(policy("max = { tokio = \"two\" }").is_err());
+        assert!(policy("unknown = true").is_err());
+    }
+}
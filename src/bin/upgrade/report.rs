@@ -0,0 +1,163 @@
+//! Report what `cargo upgrade` changed
+//!
+//! The report is all that goes to stdout, so that it stays valid JSON with `--format json`.
+//! Prompts and warnings go to stderr.
+
+use cargo_edit::{classify_change, format_table_path, ChangeKind};
+use pad::PadStr;
+use serde_json;
+use std::error::Error;
+
+/// The change of a single dependency
+#[derive(Debug, Serialize)]
+pub struct Change {
+    /// Name of the dependency
+    pub name: String,
+    /// Version requirement before the upgrade
+    pub old: String,
+    /// Version requirement after the upgrade
+    pub new: String,
+    /// How much the requirement changed
+    pub kind: ChangeKind,
+}
+
+/// The changes in one dependency section
+#[derive(Debug, Serialize)]
+struct Section {
+    /// The section, as written in the TOML header
+    section: String,
+    /// The changes of the dependencies in it
+    dependencies: Vec<Change>,
+}
+
+/// A security advisory affecting a locked version that could not be fixed by raising a
+/// requirement
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Unfixed {
+    /// The ID of the advisory
+    pub id: String,
+    /// The affected crate
+    pub package: String,
+    /// The affected version in `Cargo.lock`
+    pub version: String,
+    /// Why it could not be fixed
+    pub reason: String,
+}
+
+/// The changes of all upgraded dependencies, grouped by section
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    sections: Vec<Section>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unfixed: Vec<Unfixed>,
+}
+
+impl Report {
+    /// Record the change of a dependency.
+    pub fn add(&mut self, section: &[String], name: &str, old: &str, new: &str) {
+        self.add_in(None, section, name, old, new)
+    }
+
+    /// Record the change of a dependency in another manifest than the one being upgraded, like
+    /// the manifest of another workspace member.
+    pub fn add_in(
+        &mut self,
+        manifest: Option<&str>,
+        section: &[String],
+        name: &str,
+        old: &str,
+        new: &str,
+    ) {
+        let section = match manifest {
+            Some(manifest) => format!("{} in {}", format_table_path(section), manifest),
+            None => format_table_path(section),
+        };
+        let change = Change {
+            name: name.to_owned(),
+            old: old.to_owned(),
+            new: new.to_owned(),
+            kind: classify_change(old, new),
+        };
+
+        if let Some(existing) = self.sections.iter_mut().find(|s| s.section == section) {
+            existing.dependencies.push(change);
+            return;
+        }
+        self.sections.push(Section {
+            section: section,
+            dependencies: vec![change],
+        });
+    }
+
+    /// Record an advisory that could not be fixed, once.
+    pub fn add_unfixed(&mut self, id: &str, package: &str, version: &str, reason: &str) {
+        let unfixed = Unfixed {
+            id: id.to_owned(),
+            package: package.to_owned(),
+            version: version.to_owned(),
+            reason: reason.to_owned(),
+        };
+        if !self.unfixed.contains(&unfixed) {
+            self.unfixed.push(unfixed);
+        }
+    }
+
+    /// Whether any advisories could not be fixed
+    pub fn has_unfixed(&self) -> bool {
+        !self.unfixed.is_empty()
+    }
+
+    /// All recorded changes
+    pub fn changes<'a>(&'a self) -> Box<Iterator<Item = &'a Change> + 'a> {
+        Box::new(self.sections.iter().flat_map(|s| s.dependencies.iter()))
+    }
+
+    /// Whether there is a change of at least the given kind. Changes that cannot be classified
+    /// always count.
+    pub fn has_changes(&self, threshold: ChangeKind) -> bool {
+        self.changes()
+            .any(|c| c.kind != ChangeKind::Unchanged && c.kind >= threshold)
+    }
+
+    /// Print the report as aligned columns.
+    pub fn print_text(&self) {
+        let width = |column: fn(&Change) -> &str| {
+            self.changes().map(|c| column(c).len()).max().unwrap_or(0)
+        };
+        let name_width = width(|c| &c.name);
+        let old_width = width(|c| &c.old);
+        let new_width = width(|c| &c.new);
+
+        for section in &self.sections {
+            println!("{}:", section.section);
+            for change in &section.dependencies {
+                println!(
+                    "    {} {} -> {} {}",
+                    change.name.pad_to_width(name_width),
+                    change.old.pad_to_width(old_width),
+                    change.new.pad_to_width(new_width),
+                    change.kind
+                );
+            }
+        }
+
+        if !self.unfixed.is_empty() {
+            println!("unfixed advisories:");
+        }
+        for unfixed in &self.unfixed {
+            println!(
+                "    {} {} {}: {}",
+                unfixed.id,
+                unfixed.package,
+                unfixed.version,
+                unfixed.reason
+            );
+        }
+    }
+
+    /// Print the report as JSON.
+    pub fn print_json(&self) -> Result<(), Box<Error>> {
+        println!("{}", serde_json::to_string_pretty(self)?);
+        Ok(())
+    }
+}
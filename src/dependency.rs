@@ -14,6 +14,7 @@ pub struct Dependency {
     /// The name of the dependency (as it is set in its `Cargo.toml` and known to crates.io)
     pub name: String,
     optional: bool,
+    features: Option<Vec<String>>,
     source: DependencySource,
 }
 
@@ -22,6 +23,7 @@ impl Default for Dependency {
         Dependency {
             name: "".into(),
             optional: false,
+            features: None,
             source: DependencySource::Version("0.1.0".into()),
         }
     }
@@ -60,6 +62,17 @@ impl Dependency {
         self
     }
 
+    /// Set the features to activate for the dependency
+    pub fn set_features(mut self, features: Option<Vec<String>>) -> Dependency {
+        self.features = features;
+        self
+    }
+
+    /// Get the features to activate for the dependency
+    pub fn features(&self) -> Option<&[String]> {
+        self.features.as_ref().map(|features| features.as_slice())
+    }
+
     /// Get version of dependency
     pub fn version(&self) -> Option<&str> {
         if let DependencySource::Version(ref version) = self.source {
@@ -72,14 +85,15 @@ impl Dependency {
     /// Convert dependency to TOML
     ///
     /// Returns a tuple with the dependency's name and either the version as a `String` or the
-    /// path/git repository as a `Table`. (If the dependency is set as `optional`, a `Table` is
-    /// returned in any case.)
+    /// path/git repository as a `Table`. (If the dependency is set as `optional` or has
+    /// `features`, a `Table` is returned in any case.)
     pub fn to_toml(&self) -> (String, toml::Value) {
-        let data: toml::Value = match (self.optional, self.source.clone()) {
+        let has_features = self.features.is_some();
+        let data: toml::Value = match (self.optional, has_features, self.source.clone()) {
             // Extra short when version flag only
-            (false, DependencySource::Version(v)) => toml::Value::String(v),
+            (false, false, DependencySource::Version(v)) => toml::Value::String(v),
             // Other cases are represented as tables
-            (optional, source) => {
+            (optional, _, source) => {
                 let mut data = BTreeMap::new();
 
                 match source {
@@ -96,6 +110,13 @@ impl Dependency {
                 if self.optional {
                     data.insert("optional".into(), toml::Value::Boolean(optional));
                 }
+                if let Some(ref features) = self.features {
+                    let features = features
+                        .iter()
+                        .map(|feature| toml::Value::String(feature.clone()))
+                        .collect();
+                    data.insert("features".into(), toml::Value::Array(features));
+                }
 
                 toml::Value::Table(data)
             }
@@ -0,0 +1,321 @@
+use manifest::Manifest;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml;
+
+/// Find the crate roots `extern crate` declarations can go in: the library root and the
+/// `src/main.rs` binary, in that order, if they exist.
+///
+/// `crate_root` is the directory the manifest is in.
+pub fn find_crate_roots(manifest: &Manifest, crate_root: &Path) -> Vec<PathBuf> {
+    let lib = manifest
+        .data
+        .get("lib")
+        .and_then(|lib| lib.get("path"))
+        .and_then(toml::Value::as_str)
+        .unwrap_or("src/lib.rs");
+
+    [lib, "src/main.rs"]
+        .iter()
+        .map(|path| crate_root.join(path))
+        .filter(|path| fs::metadata(path).map(|m| m.is_file()).unwrap_or(false))
+        .collect()
+}
+
+/// Insert an `extern crate` declaration for a dependency into the source of a crate root.
+///
+/// The declaration is added after the last existing top-level one, or after the crate's inner
+/// attributes and documentation if there is none yet. `attributes` (e.g. `#[macro_use]`) are put
+/// on the lines before it. Returns `None` if the crate is already declared.
+pub fn add_extern_crate(source: &str, name: &str, attributes: &[String]) -> Option<String> {
+    let ident = name.replace('-', "_");
+    let lines: Vec<&str> = source.split('\n').collect();
+    let top_level = find_top_level(&lines);
+    if (0..lines.len()).any(|i| top_level[i] && declares(lines[i], &ident)) {
+        return None;
+    }
+
+    let mut declaration: Vec<String> = attributes.to_vec();
+    declaration.push(format!("extern crate {};", ident));
+
+    let mut result: Vec<String> = lines.iter().map(|&line| line.to_owned()).collect();
+    match (0..lines.len()).rposition(|i| top_level[i] && is_extern_crate(lines[i])) {
+        Some(last) => {
+            for (i, line) in declaration.into_iter().enumerate() {
+                result.insert(last + 1 + i, line);
+            }
+        }
+        None => {
+            let header = header_length(&lines);
+            let mut block = Vec::new();
+            if header > 0 {
+                block.push(String::new());
+            }
+            block.extend(declaration);
+            if lines.get(header).map_or(false, |line| !line.trim().is_empty()) {
+                block.push(String::new());
+            }
+            for (i, line) in block.into_iter().enumerate() {
+                result.insert(header + i, line);
+            }
+        }
+    }
+
+    Some(result.join("\n"))
+}
+
+/// Remove the top-level `extern crate` declaration of a dependency, along with the attributes on
+/// it, from the source of a crate root. Returns `None` if the crate is not declared.
+pub fn remove_extern_crate(source: &str, name: &str) -> Option<String> {
+    let ident = name.replace('-', "_");
+    let mut lines: Vec<&str> = source.split('\n').collect();
+    let top_level = find_top_level(&lines);
+    let position = (0..lines.len()).position(|i| top_level[i] && declares(lines[i], &ident));
+    let position = match position {
+        Some(position) => position,
+        None => return None,
+    };
+
+    let mut start = position;
+    while start > 0 && is_outer_attribute(lines[start - 1]) {
+        start -= 1;
+    }
+    lines.drain(start..position + 1);
+
+    Some(lines.join("\n"))
+}
+
+/// Add an `extern crate` declaration to a file, see `add_extern_crate`.
+///
+/// Returns whether the file was changed.
+pub fn add_extern_crate_to_file(
+    path: &Path,
+    name: &str,
+    attributes: &[String],
+) -> Result<bool, io::Error> {
+    let source = read_file(path)?;
+    match add_extern_crate(&source, name, attributes) {
+        Some(source) => write_file(path, &source).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Remove an `extern crate` declaration from a file, see `remove_extern_crate`.
+///
+/// Returns whether the file was changed.
+pub fn remove_extern_crate_from_file(path: &Path, name: &str) -> Result<bool, io::Error> {
+    let source = read_file(path)?;
+    match remove_extern_crate(&source, name) {
+        Some(source) => write_file(path, &source).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, io::Error> {
+    let mut source = String::new();
+    File::open(path)?.read_to_string(&mut source)?;
+    Ok(source)
+}
+
+fn write_file(path: &Path, source: &str) -> Result<(), io::Error> {
+    File::create(path)?.write_all(source.as_bytes())
+}
+
+/// Strip `extern crate `, or `pub extern crate `, from the start of a line
+fn strip_extern_crate(line: &str) -> Option<&str> {
+    let line = if line.starts_with("pub ") {
+        line["pub ".len()..].trim_left()
+    } else {
+        line
+    };
+
+    if line.starts_with("extern crate ") {
+        Some(&line["extern crate ".len()..])
+    } else {
+        None
+    }
+}
+
+fn is_extern_crate(line: &str) -> bool {
+    strip_extern_crate(line).is_some()
+}
+
+/// Whether a line is `extern crate <ident>;`, possibly public or renamed with `as`
+fn declares(line: &str, ident: &str) -> bool {
+    let line = line.trim_right();
+    match strip_extern_crate(line) {
+        Some(rest) if rest.ends_with(';') => {
+            rest[..rest.len() - 1].split_whitespace().next() == Some(ident)
+        }
+        _ => false,
+    }
+}
+
+/// Find the lines that can hold a top-level item: those that are not indented and do not start
+/// inside braces, e.g. the body of a module or function.
+///
+/// Braces in line comments are ignored, those in strings are not told apart from code.
+fn find_top_level(lines: &[&str]) -> Vec<bool> {
+    let mut depth = 0i32;
+
+    lines
+        .iter()
+        .map(|line| {
+            let top_level = depth <= 0 && !line.starts_with(char::is_whitespace);
+            let code = line.find("//").map_or(*line, |comment| &line[..comment]);
+            for c in code.chars() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            top_level
+        })
+        .collect()
+}
+
+fn is_outer_attribute(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("#[") && line.ends_with(']')
+}
+
+/// Number of lines at the start of a crate root that hold its documentation, inner attributes
+/// and comments
+fn header_length(lines: &[&str]) -> usize {
+    let mut length = 0;
+    let mut open_brackets = 0i32;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if open_brackets > 0 || trimmed.starts_with("#![") {
+            for c in trimmed.chars() {
+                match c {
+                    '[' => open_brackets += 1,
+                    ']' => open_brackets -= 1,
+                    _ => {}
+                }
+            }
+            length = i + 1;
+        } else if trimmed.starts_with("//") {
+            length = i + 1;
+        } else if !trimmed.is_empty() {
+            break;
+        }
+    }
+
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &'static str = r#"//! A crate
+
+#![warn(missing_docs,
+        unused_qualifications)]
+
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json as json;
+
+mod fetch;
+"#;
+
+    #[test]
+    fn add_after_existing_declarations() {
+        let source = add_extern_crate(SOURCE, "lazy-static", &["#[macro_use]".to_owned()]);
+        assert_eq!(
+            source.unwrap(),
+            r#"//! A crate
+
+#![warn(missing_docs,
+        unused_qualifications)]
+
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json as json;
+#[macro_use]
+extern crate lazy_static;
+
+mod fetch;
+"#
+        );
+    }
+
+    #[test]
+    fn add_after_inner_attributes() {
+        let source = "//! A crate\n#![deny(warnings)]\n\nmod fetch;\n";
+        assert_eq!(
+            add_extern_crate(source, "toml", &[]).unwrap(),
+            "//! A crate\n#![deny(warnings)]\n\nextern crate toml;\n\nmod fetch;\n"
+        );
+
+        assert_eq!(
+            add_extern_crate("fn main() {}\n", "toml", &[]).unwrap(),
+            "extern crate toml;\n\nfn main() {}\n"
+        );
+    }
+
+    #[test]
+    fn add_existing_declaration() {
+        assert!(add_extern_crate(SOURCE, "serde-json", &[]).is_none());
+    }
+
+    #[test]
+    fn remove_declaration_with_attributes() {
+        assert_eq!(
+            remove_extern_crate(SOURCE, "serde_json").unwrap(),
+            r#"//! A crate
+
+#![warn(missing_docs,
+        unused_qualifications)]
+
+#[macro_use]
+extern crate serde_derive;
+
+mod fetch;
+"#
+        );
+
+        assert_eq!(
+            remove_extern_crate(SOURCE, "serde_derive").unwrap(),
+            r#"//! A crate
+
+#![warn(missing_docs,
+        unused_qualifications)]
+
+#[cfg(feature = "json")]
+extern crate serde_json as json;
+
+mod fetch;
+"#
+        );
+    }
+
+    #[test]
+    fn add_ignores_nested_declarations() {
+        let source = "pub extern crate toml;\n\nmod inner {\n    extern crate serde;\n}\n\n\
+                      fn main() {\nextern crate regex;\n}\n";
+        assert!(add_extern_crate(source, "toml", &[]).is_none());
+        assert_eq!(
+            add_extern_crate(source, "serde", &[]).unwrap(),
+            "pub extern crate toml;\nextern crate serde;\n\nmod inner {\n    extern crate serde;\n\
+             }\n\nfn main() {\nextern crate regex;\n}\n"
+        );
+        assert!(remove_extern_crate(source, "regex").is_none());
+        assert_eq!(
+            remove_extern_crate(source, "toml").unwrap(),
+            "\nmod inner {\n    extern crate serde;\n}\n\nfn main() {\nextern crate regex;\n}\n"
+        );
+    }
+
+    #[test]
+    fn remove_missing_declaration() {
+        assert!(remove_extern_crate(SOURCE, "serde").is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Enumeration of errors which can occur when checking features against a crate's feature table.
+quick_error! {
+    #[derive(Debug)]
+    pub enum FeatureError {
+        /// The crate does not declare the requested feature.
+        UnknownFeature(feature: String, crate_name: String, version: String,
+                       suggestion: Option<String>) {
+            description("unknown feature")
+            display("`{}` v{} has no feature `{}`.{}", crate_name, version, feature,
+                match *suggestion {
+                    Some(ref s) => format!(" Did you mean `{}`?", s),
+                    None => "".to_string(),
+                })
+        }
+    }
+}
+
+/// The features a published version of a crate declares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateFeatures {
+    /// The name of the crate
+    pub name: String,
+    /// The version the feature table belongs to
+    pub version: String,
+    /// The declared features, mapped to the features and dependencies they enable
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+impl CrateFeatures {
+    /// Add the optional dependencies of the crate as features.
+    ///
+    /// Cargo makes every optional dependency a feature of the same name, unless a feature
+    /// enables it with `dep:name`.
+    pub fn with_optional_dependencies(mut self, dependencies: &[String]) -> CrateFeatures {
+        for dependency in dependencies {
+            let explicit = format!("dep:{}", dependency);
+            let hidden = self.features
+                .values()
+                .any(|enables| enables.iter().any(|f| *f == explicit));
+            if !hidden {
+                self.features
+                    .entry(dependency.clone())
+                    .or_insert_with(Vec::new);
+            }
+        }
+        self
+    }
+
+    /// Check that every requested feature is declared by the crate.
+    ///
+    /// Features of dependencies (`dep/feature`) cannot be checked against the crate's own feature
+    /// table and are accepted as they are.
+    pub fn validate(&self, requested: &[String]) -> Result<(), FeatureError> {
+        for feature in requested {
+            if feature.contains('/') || self.features.contains_key(feature) {
+                continue;
+            }
+
+            return Err(FeatureError::UnknownFeature(
+                feature.clone(),
+                self.name.clone(),
+                self.version.clone(),
+                self.suggest(feature),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Features that are activated by `default`, directly or through other features
+    pub fn default_features(&self) -> BTreeSet<String> {
+        self.activated(&["default".to_owned()])
+    }
+
+    /// Features that are activated by the given features, directly or through other features
+    ///
+    /// The result contains the given features themselves, but never `default`.
+    pub fn activated(&self, features: &[String]) -> BTreeSet<String> {
+        let mut activated = BTreeSet::new();
+        let mut pending: Vec<String> = features.to_vec();
+
+        while let Some(feature) = pending.pop() {
+            if !self.features.contains_key(&feature) || !activated.insert(feature.clone()) {
+                continue;
+            }
+            pending.extend(self.features[&feature].iter().cloned());
+        }
+
+        activated.remove("default");
+        activated
+    }
+
+    /// Features that are declared, but not activated by the given features
+    pub fn available(&self, activated: &BTreeSet<String>) -> BTreeSet<String> {
+        self.features
+            .keys()
+            .filter(|&f| f != "default" && !activated.contains(f))
+            .cloned()
+            .collect()
+    }
+
+    /// The declared feature that is closest to a misspelled one, if any is close enough
+    fn suggest(&self, feature: &str) -> Option<String> {
+        self.features
+            .keys()
+            .map(|f| (lev_distance(f, feature), f))
+            .filter(|&(distance, _)| distance < 4)
+            .min_by_key(|&(distance, _)| distance)
+            .map(|(_, f)| f.clone())
+    }
+}
+
+/// Levenshtein distance between two strings
+fn lev_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == cb { 0 } else { 1 };
+            current.push(cmp::min(
+                substitution,
+                cmp::min(previous[j + 1] + 1, current[j] + 1),
+            ));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features() -> CrateFeatures {
+        let mut features = BTreeMap::new();
+        features.insert("default".to_owned(), vec!["std".to_owned()]);
+        features.insert("std".to_owned(), vec!["alloc".to_owned()]);
+        features.insert("alloc".to_owned(), vec![]);
+        features.insert("serde".to_owned(), vec!["serde_derive".to_owned()]);
+        CrateFeatures {
+            name: "foo".to_owned(),
+            version: "1.2.3".to_owned(),
+            features: features,
+        }
+    }
+
+    #[test]
+    fn validate_known_features() {
+        let features = features();
+        assert!(
+            features
+                .validate(&["serde".to_owned(), "bar/baz".to_owned()])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_suggests_closest_feature() {
+        let err = features().validate(&["sedre".to_owned()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`foo` v1.2.3 has no feature `sedre`. Did you mean `serde`?"
+        );
+
+        let err = features()
+            .validate(&["completely-different".to_owned()])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`foo` v1.2.3 has no feature `completely-different`."
+        );
+    }
+
+    #[test]
+    fn optional_dependencies_are_features() {
+        // Like `chrono`, which has an optional dependency on `serde`, but no feature of that name
+        let mut features = BTreeMap::new();
+        features.insert("default".to_owned(), vec!["clock".to_owned()]);
+        features.insert("clock".to_owned(), vec![]);
+        features.insert("unstable-locales".to_owned(), vec!["dep:pure-rust-locales".to_owned()]);
+        let features = CrateFeatures {
+            name: "chrono".to_owned(),
+            version: "0.4.6".to_owned(),
+            features: features,
+        };
+        assert!(features.validate(&["serde".to_owned()]).is_err());
+
+        let features = features.with_optional_dependencies(&[
+            "serde".to_owned(),
+            "pure-rust-locales".to_owned(),
+        ]);
+        assert!(features.validate(&["serde".to_owned()]).is_ok());
+        // Hidden behind `dep:`
+        assert!(features.validate(&["pure-rust-locales".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn default_features_are_transitive() {
+        let features = features();
+        let default = features.default_features();
+        assert_eq!(
+            default.into_iter().collect::<Vec<_>>(),
+            vec!["alloc", "std"]
+        );
+    }
+
+    #[test]
+    fn available_features_exclude_activated() {
+        let features = features();
+        let activated = features.activated(&["default".to_owned(), "alloc".to_owned()]);
+        assert_eq!(
+            features.available(&activated).into_iter().collect::<Vec<_>>(),
+            vec!["serde"]
+        );
+    }
+
+    #[test]
+    fn lev_distance_works() {
+        assert_eq!(lev_distance("serde", "serde"), 0);
+        assert_eq!(lev_distance("serde", "sedre"), 2);
+        assert_eq!(lev_distance("", "abc"), 3);
+    }
+}
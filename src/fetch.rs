@@ -1,16 +1,33 @@
-use {Dependency, Manifest};
+use {CrateFeatures, Dependency, Manifest};
 use regex::Regex;
 use reqwest;
 use semver;
+use serde::de::DeserializeOwned;
 use serde_json as json;
+use std::collections::BTreeMap;
 use std::env;
+use std::fs::File;
 use std::io;
-use std::io::Read;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
+use version::lowest_version;
 
 const REGISTRY_HOST: &'static str = "https://crates.io";
 
+/// Environment variable naming a directory that the tests read crates.io responses from instead.
+/// It is ignored unless `CARGO_IS_TEST` is set as well, see `get_test_registry`.
+const TEST_REGISTRY_VAR: &'static str = "CARGO_EDIT_TEST_REGISTRY";
+
+/// Where the registry queries of the tests are answered from
+enum TestRegistry {
+    /// Made-up answers, e.g. `<name>--CURRENT_VERSION_TEST` for the latest version
+    Simulated,
+    /// The crates.io responses stored in a directory, as `<dir><path>.json`
+    Fixtures(PathBuf),
+}
+
 #[derive(Deserialize)]
 struct Versions {
     versions: Vec<CrateVersion>,
@@ -23,6 +40,61 @@ struct CrateVersion {
     #[serde(rename = "num")]
     version: semver::Version,
     yanked: bool,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Dependencies {
+    dependencies: Vec<VersionDependency>,
+}
+
+/// A dependency of a published version of a crate
+#[derive(Deserialize)]
+struct VersionDependency {
+    crate_id: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+/// A date (`YYYY-MM-DD`, UTC) before which a version must have been published to be considered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedBefore(String);
+
+impl PublishedBefore {
+    /// Check whether a version published at `created_at` (an RFC 3339 timestamp in UTC, as
+    /// reported by crates.io) was published before this date.
+    fn includes(&self, created_at: &str) -> bool {
+        // Both are written most significant part first, so comparing them as strings compares
+        // them as points in time. A timestamp on the day itself is longer, and thus later.
+        created_at < self.0.as_str()
+    }
+}
+
+impl FromStr for PublishedBefore {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let re = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+        let valid = re.captures(input)
+            .map(|cap| {
+                let month: u32 = cap[2].parse().unwrap_or(0);
+                let day: u32 = cap[3].parse().unwrap_or(0);
+                month >= 1 && month <= 12 && day >= 1 && day <= 31
+            })
+            .unwrap_or(false);
+
+        if valid {
+            Ok(PublishedBefore(input.to_owned()))
+        } else {
+            Err(format!(
+                "Invalid date `{}`, expected a date like `2024-03-01`.",
+                input
+            ))
+        }
+    }
 }
 
 /// Query latest version from crates.io
@@ -32,11 +104,14 @@ struct CrateVersion {
 /// - there is no Internet connection,
 /// - the response from crates.io is an error or in an incorrect format,
 /// - or when a crate with the given name does not exist on crates.io.
+///
+/// If `before` is given, only versions published before that date are considered.
 pub fn get_latest_dependency(
     crate_name: &str,
     flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
 ) -> Result<Dependency, FetchVersionError> {
-    if env::var("CARGO_IS_TEST").is_ok() {
+    if let Some(TestRegistry::Simulated) = get_test_registry() {
         // We are in a simulated reality. Nothing is real here.
         // FIXME: Use actual test handling code.
         return Ok(
@@ -47,20 +122,181 @@ pub fn get_latest_dependency(
 
     let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
 
-    let dep = read_latest_version(&crate_versions, flag_allow_prerelease)?;
+    let dep = read_latest_version(&crate_versions, flag_allow_prerelease, before)?;
 
     if dep.name != crate_name {
-        println!("WARN: Added `{}` instead of `{}`", dep.name, crate_name);
+        writeln!(
+            io::stderr(),
+            "WARN: Added `{}` instead of `{}`",
+            dep.name,
+            crate_name
+        ).unwrap();
     }
 
     Ok(dep)
 }
 
+/// Query the newest version matching a version requirement from crates.io
+///
+/// The matching version will be returned as a `Dependency`. Prerelease versions are only
+/// considered if `flag_allow_prerelease` is set, or if the requirement itself names a prerelease.
+/// If `before` is given, only versions published before that date are considered. This will
+/// fail, when
+///
+/// - `version_req` is not a valid version requirement,
+/// - there is no Internet connection,
+/// - the response from crates.io is an error or in an incorrect format,
+/// - or when no published, non-yanked version of the crate matches `version_req`.
+pub fn get_compatible_dependency(
+    crate_name: &str,
+    version_req: &str,
+    flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
+) -> Result<Dependency, FetchVersionError> {
+    let req = semver::VersionReq::parse(version_req)?;
+
+    if let Some(TestRegistry::Simulated) = get_test_registry() {
+        // We are in a simulated reality. Every requirement is matched by a version that looks
+        // just like it.
+        return Ok(Dependency::new(crate_name).set_version(version_req));
+    }
+
+    let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    let flag_allow_prerelease = flag_allow_prerelease || version_req.contains('-');
+
+    let dep = read_compatible_version(&crate_versions, &req, flag_allow_prerelease, before)
+        .map_err(|_| FetchVersionError::NoneMatching(crate_name.into(), version_req.into()))?;
+
+    if dep.name != crate_name {
+        writeln!(
+            io::stderr(),
+            "WARN: Added `{}` instead of `{}`",
+            dep.name,
+            crate_name
+        ).unwrap();
+    }
+
+    Ok(dep)
+}
+
+/// Query the oldest version matching a version requirement from crates.io
+///
+/// The matching version will be returned as a `Dependency`. Yanked versions are skipped, and
+/// prerelease versions are only considered if `flag_allow_prerelease` is set, or if the
+/// requirement itself names a prerelease. This will fail, when
+///
+/// - `version_req` is not a valid version requirement,
+/// - there is no Internet connection,
+/// - the response from crates.io is an error or in an incorrect format,
+/// - or when no published, non-yanked version of the crate matches `version_req`.
+pub fn get_minimal_dependency(
+    crate_name: &str,
+    version_req: &str,
+    flag_allow_prerelease: bool,
+) -> Result<Dependency, FetchVersionError> {
+    let req = semver::VersionReq::parse(version_req)?;
+
+    if let Some(TestRegistry::Simulated) = get_test_registry() {
+        // We are in a simulated reality. Every version a requirement allows is available.
+        let version = lowest_version(version_req).map_or(version_req.to_owned(), |v| v.to_string());
+        return Ok(Dependency::new(crate_name).set_version(&version));
+    }
+
+    let crate_versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    let flag_allow_prerelease = flag_allow_prerelease || version_req.contains('-');
+
+    read_minimal_version(&crate_versions, &req, flag_allow_prerelease)
+        .map_err(|_| FetchVersionError::NoneMatching(crate_name.into(), version_req.into()))
+}
+
+/// Query the features of the version of a crate that a version requirement resolves to
+///
+/// The version is chosen like `get_compatible_dependency` does, so prereleases and versions
+/// published after `before` are left out the same way. Optional dependencies of that version are
+/// features too, unless a feature refers to them as `dep:name`. This will fail, when
+///
+/// - there is no Internet connection,
+/// - the response from crates.io is an error or in an incorrect format,
+/// - `version_req` is not a valid version requirement,
+/// - or when no published version of the crate matches `version_req`.
+pub fn get_crate_features(
+    crate_name: &str,
+    version_req: &str,
+    flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
+) -> Result<CrateFeatures, FetchVersionError> {
+    if let Some(TestRegistry::Simulated) = get_test_registry() {
+        // We are in a simulated reality. Every crate has the same features, and an optional
+        // dependency on `log`.
+        let mut features = BTreeMap::new();
+        features.insert("default".to_owned(), vec!["std".to_owned()]);
+        features.insert("std".to_owned(), vec![]);
+        features.insert("serde".to_owned(), vec![]);
+        return Ok(CrateFeatures {
+            name: crate_name.into(),
+            version: version_req.into(),
+            features: features,
+        }.with_optional_dependencies(&["log".to_owned()]));
+    }
+
+    let req = semver::VersionReq::parse(version_req)?;
+    let crate_versions: Versions = fetch_cratesio(&format!("/crates/{}", crate_name))?;
+    let flag_allow_prerelease = flag_allow_prerelease || version_req.contains('-');
+
+    let features = read_crate_features(&crate_versions, &req, flag_allow_prerelease, before)?;
+    let dependencies: Dependencies = fetch_cratesio(&format!(
+        "/crates/{}/{}/dependencies",
+        features.name,
+        features.version
+    ))?;
+
+    Ok(features.with_optional_dependencies(&read_optional_dependencies(&dependencies)))
+}
+
+/// Read the feature table of the version `read_compatible_version` would pick
+fn read_crate_features(
+    versions: &Versions,
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
+) -> Result<CrateFeatures, FetchVersionError> {
+    let matching = find_compatible_version(versions, version_req, flag_allow_prerelease, before)
+        .ok_or_else(|| {
+            FetchVersionError::NoneMatching(versions_name(versions), version_req.to_string())
+        })?;
+
+    Ok(CrateFeatures {
+        name: matching.name.clone(),
+        version: matching.version.to_string(),
+        features: matching.features.clone(),
+    })
+}
+
+/// The names of the optional dependencies of a version
+fn read_optional_dependencies(dependencies: &Dependencies) -> Vec<String> {
+    dependencies
+        .dependencies
+        .iter()
+        .filter(|dep| dep.optional)
+        .map(|dep| dep.crate_id.clone())
+        .collect()
+}
+
 // Checks whether a version object is a stable release
 fn version_is_stable(version: &CrateVersion) -> bool {
     !version.version.is_prerelease()
 }
 
+// Checks whether a version object was published before the given date (if any)
+fn version_is_published_before(version: &CrateVersion, before: Option<&PublishedBefore>) -> bool {
+    match (before, version.created_at.as_ref()) {
+        (None, _) => true,
+        (Some(before), Some(created_at)) => before.includes(created_at),
+        // Without a publication date, we cannot tell.
+        (Some(_), None) => false,
+    }
+}
+
 /// Read latest version from Versions structure
 ///
 /// Assumes the version are sorted so that the first non-yanked version is the
@@ -68,19 +304,76 @@ fn version_is_stable(version: &CrateVersion) -> bool {
 fn read_latest_version(
     versions: &Versions,
     flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
+) -> Result<Dependency, FetchVersionError> {
+    let any = semver::VersionReq::any();
+    read_compatible_version(versions, &any, flag_allow_prerelease, before)
+}
+
+/// Read the latest version matching a requirement from Versions structure
+///
+/// Makes the same assumptions about the order of the versions as `read_latest_version`.
+fn read_compatible_version(
+    versions: &Versions,
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
 ) -> Result<Dependency, FetchVersionError> {
-    let latest = versions
+    let latest = find_compatible_version(versions, version_req, flag_allow_prerelease, before)
+        .ok_or(FetchVersionError::NoneAvailable)?;
+
+    let name = &latest.name;
+    let version = latest.version.to_string();
+    Ok(Dependency::new(name).set_version(&version))
+}
+
+/// Find the newest non-yanked version matching a requirement
+fn find_compatible_version<'a>(
+    versions: &'a Versions,
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+    before: Option<&PublishedBefore>,
+) -> Option<&'a CrateVersion> {
+    versions
         .versions
         .iter()
         .filter(|&v| flag_allow_prerelease || version_is_stable(v))
+        .filter(|&v| version_req.matches(&v.version))
+        .filter(|&v| version_is_published_before(v, before))
         .find(|&v| !v.yanked)
+}
+
+/// Read the oldest version matching a requirement from Versions structure
+///
+/// Unlike `read_compatible_version`, this does not depend on the order of the versions.
+fn read_minimal_version(
+    versions: &Versions,
+    version_req: &semver::VersionReq,
+    flag_allow_prerelease: bool,
+) -> Result<Dependency, FetchVersionError> {
+    let minimal = versions
+        .versions
+        .iter()
+        .filter(|&v| flag_allow_prerelease || version_is_stable(v))
+        .filter(|&v| version_req.matches(&v.version))
+        .filter(|&v| !v.yanked)
+        .min_by(|a, b| a.version.cmp(&b.version))
         .ok_or(FetchVersionError::NoneAvailable)?;
 
-    let name = &latest.name;
-    let version = latest.version.to_string();
+    let name = &minimal.name;
+    let version = minimal.version.to_string();
     Ok(Dependency::new(name).set_version(&version))
 }
 
+/// The name of the crate a Versions structure describes
+fn versions_name(versions: &Versions) -> String {
+    versions
+        .versions
+        .first()
+        .map(|v| v.name.clone())
+        .unwrap_or_default()
+}
+
 #[test]
 fn get_latest_stable_version_from_json() {
     let versions: Versions = json::from_str(
@@ -101,7 +394,7 @@ fn get_latest_stable_version_from_json() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, false)
+        read_latest_version(&versions, false, None)
             .unwrap()
             .version()
             .unwrap(),
@@ -129,7 +422,7 @@ fn get_latest_unstable_or_stable_version_from_json() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, true)
+        read_latest_version(&versions, true, None)
             .unwrap()
             .version()
             .unwrap(),
@@ -157,7 +450,7 @@ fn get_latest_version_from_json_test() {
     ).expect("crate version is correctly parsed");
 
     assert_eq!(
-        read_latest_version(&versions, false)
+        read_latest_version(&versions, false, None)
             .unwrap()
             .version()
             .unwrap(),
@@ -184,7 +477,247 @@ fn get_no_latest_version_from_json_when_all_are_yanked() {
     }"#,
     ).expect("crate version is correctly parsed");
 
-    assert!(read_latest_version(&versions, false).is_err());
+    assert!(read_latest_version(&versions, false, None).is_err());
+}
+
+#[test]
+fn get_latest_matching_version_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.4.0",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.3.8",
+          "yanked": true
+        },
+        {
+          "crate": "foo",
+          "num": "0.3.7",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "0.2.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("~0.3").unwrap();
+    assert_eq!(
+        read_compatible_version(&versions, &req, false, None)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.3.7"
+    );
+
+    let req = semver::VersionReq::parse("^0.5").unwrap();
+    assert!(read_compatible_version(&versions, &req, false, None).is_err());
+}
+
+#[test]
+fn get_latest_version_published_before_date_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.3.0",
+          "yanked": false,
+          "created_at": "2024-03-01T00:00:01.123456+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.2.0",
+          "yanked": false,
+          "created_at": "2024-02-29T23:59:59.999999+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.1.0",
+          "yanked": false,
+          "created_at": "2023-01-01T10:00:00.000000+00:00"
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let before: PublishedBefore = "2024-03-01".parse().unwrap();
+    assert_eq!(
+        read_latest_version(&versions, false, Some(&before))
+            .unwrap()
+            .version()
+            .unwrap(),
+        "0.2.0"
+    );
+
+    let before: PublishedBefore = "2023-01-01".parse().unwrap();
+    assert!(read_latest_version(&versions, false, Some(&before)).is_err());
+}
+
+#[test]
+fn get_features_of_version_published_before_date_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "0.3.1",
+          "yanked": false,
+          "features": { "serde": [], "std": [] },
+          "created_at": "2024-03-01T00:00:01.123456+00:00"
+        },
+        {
+          "crate": "foo",
+          "num": "0.3.0",
+          "yanked": false,
+          "features": { "std": [] },
+          "created_at": "2024-02-29T23:59:59.999999+00:00"
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("0.3").unwrap();
+    let features = read_crate_features(&versions, &req, false, None).unwrap();
+    assert_eq!(features.version, "0.3.1");
+    assert!(features.features.contains_key("serde"));
+
+    let before: PublishedBefore = "2024-03-01".parse().unwrap();
+    let features = read_crate_features(&versions, &req, false, Some(&before)).unwrap();
+    assert_eq!(features.version, "0.3.0");
+    assert!(!features.features.contains_key("serde"));
+}
+
+#[test]
+fn get_optional_dependencies_from_json() {
+    let dependencies: Dependencies = json::from_str(
+        r#"{
+      "dependencies": [
+        {
+          "crate_id": "num-traits",
+          "kind": "normal",
+          "optional": false,
+          "req": "^0.2"
+        },
+        {
+          "crate_id": "serde",
+          "kind": "normal",
+          "optional": true,
+          "req": "^1"
+        }
+      ]
+    }"#,
+    ).expect("dependencies are correctly parsed");
+
+    assert_eq!(read_optional_dependencies(&dependencies), vec!["serde"]);
+}
+
+#[test]
+fn get_minimal_version_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "1.2.0",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "1.1.0-beta",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.1",
+          "yanked": false
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.0",
+          "yanked": true
+        },
+        {
+          "crate": "foo",
+          "num": "0.9.0",
+          "yanked": false
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("1.0").unwrap();
+    assert_eq!(
+        read_minimal_version(&versions, &req, false)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "1.0.1"
+    );
+
+    let req = semver::VersionReq::parse("1.1.0-alpha").unwrap();
+    assert_eq!(
+        read_minimal_version(&versions, &req, true)
+            .unwrap()
+            .version()
+            .unwrap(),
+        "1.1.0-beta"
+    );
+
+    let req = semver::VersionReq::parse("2.0").unwrap();
+    assert!(read_minimal_version(&versions, &req, false).is_err());
+}
+
+#[test]
+fn parse_published_before_date() {
+    assert!("2024-03-01".parse::<PublishedBefore>().is_ok());
+    assert!("2024-13-01".parse::<PublishedBefore>().is_err());
+    assert!("2024-3-1".parse::<PublishedBefore>().is_err());
+    assert!("yesterday".parse::<PublishedBefore>().is_err());
+}
+
+#[test]
+fn get_features_of_matching_version_from_json() {
+    let versions: Versions = json::from_str(
+        r#"{
+      "versions": [
+        {
+          "crate": "foo",
+          "num": "2.0.0",
+          "yanked": false,
+          "features": { "default": ["std"], "std": [], "nightly": [] }
+        },
+        {
+          "crate": "foo",
+          "num": "1.1.0",
+          "yanked": true,
+          "features": { "serde": [] }
+        },
+        {
+          "crate": "foo",
+          "num": "1.0.0",
+          "yanked": false,
+          "features": { "default": ["std"], "std": [] }
+        }
+      ]
+    }"#,
+    ).expect("crate version is correctly parsed");
+
+    let req = semver::VersionReq::parse("^1").unwrap();
+    let features = read_crate_features(&versions, &req, false, None).unwrap();
+    assert_eq!(features.version, "1.0.0");
+    assert_eq!(
+        features.features.keys().collect::<Vec<_>>(),
+        vec!["default", "std"]
+    );
 }
 
 quick_error! {
@@ -202,21 +735,58 @@ quick_error! {
             display("Json error (crate does not exist?): {}", err)
             cause(err)
         }
+        TestFixture(path: PathBuf, message: String) {
+            description("Invalid test registry fixture")
+            display("Could not read the test registry fixture `{}`: {}", path.display(), message)
+        }
+        SemVer(err: semver::ReqParseError) {
+            from()
+            description("Invalid version requirement")
+            display("Invalid version requirement: {}", err)
+            cause(err)
+        }
         NoneAvailable {
             description("No available versions exist. Either all were yanked\
                          or only prerelease versions exist. Trying with the\
                          --fetch-prereleases flag might solve the issue."
             )
         }
+        NoneMatching(crate_name: String, version_req: String) {
+            description("No available version matches the requirement")
+            display("No available version of `{}` matches `{}`. Either none exists, all \
+                     matching ones were yanked or only prerelease versions match.",
+                    crate_name, version_req)
+        }
     }
 }
 
-fn fetch_cratesio(path: &str) -> Result<Versions, FetchVersionError> {
+/// How the registry queries are answered when running the tests, `None` outside of them
+fn get_test_registry() -> Option<TestRegistry> {
+    if env::var("CARGO_IS_TEST").is_err() {
+        return None;
+    }
+
+    Some(match env::var_os(TEST_REGISTRY_VAR) {
+        Some(dir) => TestRegistry::Fixtures(PathBuf::from(dir)),
+        None => TestRegistry::Simulated,
+    })
+}
+
+/// Query the crates.io API at `path`, e.g. `/crates/serde`.
+///
+/// In the tests, the response is read from the fixtures of the test registry instead, e.g.
+/// `<dir>/crates/serde.json`.
+fn fetch_cratesio<T: DeserializeOwned>(path: &str) -> Result<T, FetchVersionError> {
+    if let Some(TestRegistry::Fixtures(dir)) = get_test_registry() {
+        let fixture = dir.join(format!("{}.json", &path[1..]));
+        let error = |message: String| FetchVersionError::TestFixture(fixture.clone(), message);
+        let file = File::open(&fixture).map_err(|err| error(err.to_string()))?;
+        return json::from_reader(file).map_err(|err| error(err.to_string()));
+    }
+
     let url = format!("{host}/api/v1{path}", host = REGISTRY_HOST, path = path);
     let response = get_with_timeout(&url, get_default_timeout())?;
-    let versions: Versions = json::from_reader(response)
-        .map_err(FetchVersionError::Json)?;
-    Ok(versions)
+    json::from_reader(response).map_err(FetchVersionError::Json)
 }
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -315,12 +885,27 @@ pub fn get_crate_name_from_path(path: &str) -> Result<String, FetchGitError> {
         .and_then(|ref manifest| get_name_from_manifest(manifest))
 }
 
+/// Query crate version by accessing Cargo.toml in a local path
+///
+/// The `package.version` will be returned as a string. This will fail, when
+/// Cargo.toml is not present in the root of the path.
+pub fn get_crate_version_from_path(path: &str) -> Result<String, FetchGitError> {
+    let cargo_file = Path::new(path).join("Cargo.toml");
+    Manifest::open(&Some(cargo_file))
+        .map_err(|_| FetchGitError::LocalCargoToml)
+        .and_then(|ref manifest| get_package_key_from_manifest(manifest, "version"))
+}
+
 fn get_name_from_manifest(manifest: &Manifest) -> Result<String, FetchGitError> {
+    get_package_key_from_manifest(manifest, "name")
+}
+
+fn get_package_key_from_manifest(manifest: &Manifest, key: &str) -> Result<String, FetchGitError> {
     manifest
         .data
         .get("package")
-        .and_then(|m| m.get("name"))
-        .and_then(|name| name.as_str().map(|s| s.to_string()))
+        .and_then(|m| m.get(key))
+        .and_then(|value| value.as_str().map(|s| s.to_string()))
         .ok_or(FetchGitError::ParseCargoToml)
 }
 
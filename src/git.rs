@@ -0,0 +1,129 @@
+use semver::Version;
+use std::env;
+use std::io;
+use std::process::Command;
+
+/// Enumeration of errors which can occur when listing the refs of a git repository.
+quick_error! {
+    #[derive(Debug)]
+    pub enum GitError {
+        /// `git` could not be run.
+        Io(err: io::Error) {
+            from()
+            description("could not run git")
+            display("Could not run `git`: {}", err)
+            cause(err)
+        }
+        /// `git ls-remote` failed, e.g. because the repository does not exist.
+        LsRemote(url: String, message: String) {
+            description("git ls-remote failed")
+            display("Could not list the refs of `{}`: {}", url, message)
+        }
+    }
+}
+
+/// A ref of a remote git repository, as listed by `git ls-remote`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRef {
+    /// The commit (or tag object) the ref points to
+    pub id: String,
+    /// The full name of the ref, e.g. `refs/tags/v1.2.0`
+    pub name: String,
+}
+
+/// List the refs of a git repository with `git ls-remote` (or whatever `$GIT` points to).
+///
+/// `url` can be anything `git` understands, including the path of a local (bare) repository.
+pub fn list_remote_refs(url: &str) -> Result<Vec<GitRef>, GitError> {
+    let git = env::var("GIT").unwrap_or_else(|_| "git".to_owned());
+    let output = Command::new(git).args(&["ls-remote", url]).output()?;
+
+    if !output.status.success() {
+        return Err(GitError::LsRemote(
+            url.to_owned(),
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            match (parts.next(), parts.next()) {
+                (Some(id), Some(name)) => Some(GitRef {
+                    id: id.trim().to_owned(),
+                    name: name.trim().to_owned(),
+                }),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// The tag with the highest semver version (optionally prefixed with `v`), e.g. `v1.2.0`.
+///
+/// Prerelease tags are only considered if `allow_prerelease` is set.
+pub fn find_latest_tag(refs: &[GitRef], allow_prerelease: bool) -> Option<String> {
+    refs.iter()
+        .filter(|r| r.name.starts_with("refs/tags/") && !r.name.ends_with("^{}"))
+        .map(|r| r.name["refs/tags/".len()..].to_owned())
+        .filter_map(|tag| {
+            Version::parse(tag.trim_left_matches('v'))
+                .ok()
+                .map(|version| (version, tag))
+        })
+        .filter(|&(ref version, _)| allow_prerelease || !version.is_prerelease())
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, tag)| tag)
+}
+
+/// The commit the given branch points to, or the one `HEAD` points to if no branch is given.
+pub fn find_branch_head(refs: &[GitRef], branch: Option<&str>) -> Option<String> {
+    let name = match branch {
+        Some(branch) => format!("refs/heads/{}", branch),
+        None => "HEAD".to_owned(),
+    };
+
+    refs.iter().find(|r| r.name == name).map(|r| r.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refs() -> Vec<GitRef> {
+        [
+            ("1111111111", "HEAD"),
+            ("1111111111", "refs/heads/master"),
+            ("2222222222", "refs/heads/stable"),
+            ("3333333333", "refs/tags/v0.9.0"),
+            ("4444444444", "refs/tags/v1.10.0"),
+            ("5555555555", "refs/tags/v1.10.0^{}"),
+            ("6666666666", "refs/tags/1.2.0"),
+            ("7777777777", "refs/tags/v2.0.0-beta.1"),
+            ("8888888888", "refs/tags/nightly"),
+        ].iter()
+            .map(|&(id, name)| GitRef {
+                id: id.to_owned(),
+                name: name.to_owned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_latest_tag() {
+        assert_eq!(find_latest_tag(&refs(), false), Some("v1.10.0".to_owned()));
+        assert_eq!(find_latest_tag(&refs(), true), Some("v2.0.0-beta.1".to_owned()));
+        assert_eq!(find_latest_tag(&refs()[..3], false), None);
+    }
+
+    #[test]
+    fn finds_branch_head() {
+        assert_eq!(find_branch_head(&refs(), None), Some("1111111111".to_owned()));
+        assert_eq!(
+            find_branch_head(&refs(), Some("stable")),
+            Some("2222222222".to_owned())
+        );
+        assert_eq!(find_branch_head(&refs(), Some("missing")), None);
+    }
+}
@@ -16,11 +16,28 @@ extern crate serde_derive;
 extern crate serde_json;
 extern crate toml;
 
+mod advisory;
 mod fetch;
 mod manifest;
 mod dependency;
+mod extern_crate;
+mod features;
+mod git;
+mod target;
+mod unused;
+mod version;
 
+pub use advisory::{read_advisories, Advisory, AdvisoryError};
 pub use dependency::Dependency;
-pub use fetch::{get_latest_dependency, get_crate_name_from_github, get_crate_name_from_gitlab,
-                get_crate_name_from_path};
-pub use manifest::Manifest;
+pub use extern_crate::{add_extern_crate, add_extern_crate_to_file, find_crate_roots,
+                       remove_extern_crate, remove_extern_crate_from_file};
+pub use features::{CrateFeatures, FeatureError};
+pub use fetch::{get_compatible_dependency, get_crate_features, get_latest_dependency,
+                get_crate_name_from_github, get_crate_name_from_gitlab, get_crate_name_from_path,
+                get_crate_version_from_path, get_minimal_dependency, PublishedBefore};
+pub use git::{find_branch_head, find_latest_tag, list_remote_refs, GitError, GitRef};
+pub use manifest::{format_table_path, parse_replace_key, FeatureChange, Manifest};
+pub use target::{validate_target, TargetError};
+pub use unused::{find_unused_dependencies, UnusedDependency, Usage};
+pub use version::{classify_change, is_compatible, is_pinned, lowest_version, select_locked_version,
+                  upgrade_requirement, ChangeKind};
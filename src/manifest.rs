@@ -1,7 +1,8 @@
 use dependency::Dependency;
+use regex::{self, Regex};
+use semver;
 use std::{env, str};
 use std::collections::BTreeMap;
-use std::collections::btree_map::Entry;
 use std::error::Error;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
@@ -27,6 +28,18 @@ quick_error! {
             description("non existent dependency")
             display("The dependency `{}` could not be found in `{}`.", name, table)
         }
+        /// A feature cannot have the same name as the dependency it enables.
+        FeatureNameConflict(name: String) {
+            description("feature name conflict")
+            display("The feature `{}` cannot enable a dependency of the same name. Use another \
+                     feature name, or `dep:` syntax by setting `rust-version` to 1.60 or newer.",
+                    name)
+        }
+        /// The dependency is already present.
+        DuplicateDependency(name: String, table: String) {
+            description("duplicate dependency")
+            display("The dependency `{}` is already present in `{}`.", name, table)
+        }
         ParseError(error: String, loline: usize, locol: usize, hiline: usize, hicol: usize) {
             description("parse error")
             display("{line}:{col}{upto} {error_msg}",
@@ -56,6 +69,20 @@ impl CargoFile {
     }
 }
 
+/// A change made to `[features]` while removing references to a dependency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeatureChange {
+    /// A reference to the dependency was removed from a feature
+    RemovedReference {
+        /// The feature the reference was removed from
+        feature: String,
+        /// The reference, e.g. `dep:foo` or `foo/bar`
+        value: String,
+    },
+    /// A feature that was left empty was removed
+    RemovedFeature(String),
+}
+
 /// A Cargo Manifest
 #[derive(Debug, Clone, PartialEq)]
 pub struct Manifest {
@@ -68,9 +95,17 @@ pub struct Manifest {
 /// If a manifest is specified, return that one. If a path is specified, perform a manifest search
 /// starting from there. If nothing is specified, start searching from the current directory
 /// (`cwd`).
+///
+/// When looking for a `Cargo.lock` and a `Cargo.toml` is specified, the search starts from the
+/// directory the `Cargo.toml` is in.
 fn find(specified: &Option<PathBuf>, file: CargoFile) -> Result<PathBuf, Box<Error>> {
     match *specified {
-        Some(ref path) if fs::metadata(&path)?.is_file() => Ok(path.to_owned()),
+        Some(ref path) if fs::metadata(&path)?.is_file() => match file {
+            CargoFile::Lock if path.file_name().map_or(true, |name| name != file.name()) => {
+                search(path.parent().unwrap_or_else(|| Path::new(".")), file)
+            }
+            _ => Ok(path.to_owned()),
+        },
         Some(ref path) => search(path, file),
         None => search(&env::current_dir()?, file),
     }.map_err(From::from)
@@ -89,6 +124,77 @@ fn search(dir: &Path, file: CargoFile) -> Result<PathBuf, ManifestError> {
     }
 }
 
+/// Find the directories matching a workspace member pattern like `crates/*`, relative to the
+/// workspace root. Patterns without `*` or `?` are returned as they are.
+fn expand_member_glob(root_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut matches = vec![root_dir.to_owned()];
+
+    for segment in pattern.split('/').filter(|segment| !segment.is_empty()) {
+        if !segment.contains('*') && !segment.contains('?') {
+            for dir in &mut matches {
+                dir.push(segment);
+            }
+            continue;
+        }
+
+        let matcher = Regex::new(&format!(
+            "^{}$",
+            regex::escape(segment)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".")
+        )).unwrap();
+        let mut expanded: Vec<PathBuf> = matches
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_dir() &&
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| matcher.is_match(name))
+            })
+            .collect();
+        expanded.sort();
+        matches = expanded;
+    }
+
+    matches
+}
+
+/// Split a key of `[replace]` like `foo:1.2.3` (optionally prefixed with a source URL and `#`)
+/// into the crate name and the version.
+pub fn parse_replace_key(key: &str) -> Option<(&str, &str)> {
+    let spec = key.rsplit('#').next().unwrap_or(key);
+    let mut parts = spec.rsplitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(name)) if !name.is_empty() && !version.is_empty() => {
+            Some((name, version))
+        }
+        _ => None,
+    }
+}
+
+/// Format the path of a table the way it is written in a TOML header, e.g.
+/// `target.'cfg(unix)'.dependencies`.
+pub fn format_table_path(table_path: &[String]) -> String {
+    table_path
+        .iter()
+        .map(|segment| {
+            let bare = !segment.is_empty() &&
+                segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if bare {
+                segment.clone()
+            } else {
+                format!("'{}'", segment)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
 /// Merge a new dependency into an old entry. See `Dependency::to_toml` for what the format of the
 /// new dependency will be.
 fn merge_dependencies(old_dep: &mut toml::value::Value, new: &Dependency) {
@@ -118,6 +224,11 @@ fn merge_dependencies(old_dep: &mut toml::value::Value, new: &Dependency) {
 }
 
 impl Manifest {
+    /// Look for the path of a `Cargo.toml` file, the same way `find_file` does
+    pub fn find_path(path: &Option<PathBuf>) -> Result<PathBuf, Box<Error>> {
+        find(path, CargoFile::Config)
+    }
+
     /// Look for a `Cargo.toml` file
     ///
     /// Starts at the given path an goes into its parent directories until the manifest file is
@@ -233,6 +344,230 @@ impl Manifest {
         sections
     }
 
+    /// Get all sections in the manifest that exist and might contain dependencies, including the
+    /// ones that provide or override dependencies for other manifests: `[workspace.dependencies]`,
+    /// `[patch.<registry or url>]` and `[replace]`.
+    ///
+    /// Keys in `[replace]` are package ID specifications like `foo:1.2.3`, see
+    /// `parse_replace_key`.
+    pub fn get_all_sections(&self) -> Vec<(Vec<String>, BTreeMap<String, toml::Value>)> {
+        let mut sections = self.get_sections();
+
+        if let Some(table) = self.data
+            .get("workspace")
+            .and_then(|workspace| workspace.get("dependencies"))
+            .and_then(toml::Value::as_table)
+        {
+            sections.push((
+                vec!["workspace".to_owned(), "dependencies".to_owned()],
+                table.clone(),
+            ));
+        }
+
+        let patches = self.data
+            .get("patch")
+            .and_then(toml::Value::as_table)
+            .into_iter()
+            .flat_map(|patches| patches.iter())
+            .filter_map(|(source, table)| {
+                table
+                    .as_table()
+                    .map(|table| (vec!["patch".to_owned(), source.clone()], table.clone()))
+            });
+        sections.extend(patches);
+
+        if let Some(table) = self.data.get("replace").and_then(toml::Value::as_table) {
+            sections.push((vec!["replace".to_owned()], table.clone()));
+        }
+
+        sections
+    }
+
+    /// Get the configuration of a tool from `[workspace.metadata.<tool>]` and
+    /// `[package.metadata.<tool>]`.
+    ///
+    /// Keys in the package's metadata take precedence over the workspace's.
+    pub fn get_tool_metadata(&self, tool: &str) -> toml::value::Table {
+        self.get_tool_metadata_in_workspace(None, tool)
+    }
+
+    /// Like `get_tool_metadata`, with the `[workspace.metadata.<tool>]` of the workspace root the
+    /// manifest belongs to (see `find_workspace_root`) as well.
+    ///
+    /// Keys in the package's metadata take precedence over the manifest's own workspace metadata,
+    /// which takes precedence over the root's.
+    pub fn get_tool_metadata_in_workspace(
+        &self,
+        root: Option<&Manifest>,
+        tool: &str,
+    ) -> toml::value::Table {
+        let mut metadata = toml::value::Table::new();
+        let sources = root.map(|root| (root, "workspace"))
+            .into_iter()
+            .chain(vec![(self, "workspace"), (self, "package")]);
+
+        for (manifest, section) in sources {
+            let table = manifest
+                .data
+                .get(section)
+                .and_then(|s| s.get("metadata"))
+                .and_then(|m| m.get(tool))
+                .and_then(toml::Value::as_table);
+
+            if let Some(table) = table {
+                metadata.extend(table.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+
+        metadata
+    }
+
+    /// Get the version requirement this manifest already has for a crate from the registry, in
+    /// any of its dependency sections.
+    ///
+    /// Renamed entries (`package = "..."`) are matched by the name of the crate they refer to.
+    pub fn get_requirement(&self, name: &str) -> Option<String> {
+        self.get_sections()
+            .into_iter()
+            .flat_map(|(_, table)| table.into_iter())
+            .filter_map(|(key, dep)| {
+                let crate_name = dep.get("package")
+                    .and_then(toml::Value::as_str)
+                    .map(|package| package.to_owned())
+                    .unwrap_or(key);
+                if crate_name != name {
+                    return None;
+                }
+
+                match dep {
+                    toml::Value::String(ref version) => Some(version.clone()),
+                    toml::Value::Table(ref table)
+                        if !table.contains_key("git") && !table.contains_key("path") =>
+                    {
+                        table
+                            .get("version")
+                            .and_then(toml::Value::as_str)
+                            .map(|version| version.to_owned())
+                    }
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    /// Get the versions of a crate from the registry that are resolved in this `Cargo.lock`, the
+    /// newest first.
+    ///
+    /// This is meant to be used on the data returned by `Manifest::open_lock_file`.
+    pub fn get_locked_versions(&self, name: &str) -> Vec<semver::Version> {
+        let mut versions: Vec<semver::Version> = self.data
+            .get("package")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flat_map(|packages| packages.iter())
+            .filter(|package| package.get("name").and_then(toml::Value::as_str) == Some(name))
+            .filter(|package| {
+                package
+                    .get("source")
+                    .and_then(toml::Value::as_str)
+                    .map_or(false, |source| source.starts_with("registry+"))
+            })
+            .filter_map(|package| package.get("version").and_then(toml::Value::as_str))
+            .filter_map(|version| semver::Version::parse(version).ok())
+            .collect();
+
+        versions.sort_by(|a, b| b.cmp(a));
+        versions.dedup();
+        versions
+    }
+
+    /// Find the root manifest of the workspace the manifest at `path` (or the process' `cwd`)
+    /// belongs to, i.e. the closest manifest with a `[workspace]` table in it or above it. Returns
+    /// its path and the opened manifest, or `None` if it is not part of a workspace.
+    pub fn find_workspace_root(
+        path: &Option<PathBuf>,
+    ) -> Result<Option<(PathBuf, Manifest)>, Box<Error>> {
+        let manifest_path = find(path, CargoFile::Config)?.canonicalize()?;
+
+        let mut dir = manifest_path.parent();
+        while let Some(current) = dir {
+            let candidate = current.join(CargoFile::Config.name());
+            if let Ok(manifest) = Manifest::open(&Some(candidate.clone())) {
+                if manifest.data.contains_key("workspace") {
+                    return Ok(Some((candidate, manifest)));
+                }
+            }
+            dir = current.parent();
+        }
+
+        Ok(None)
+    }
+
+    /// Find the manifests of the other members of the workspace the manifest at `path` (or the
+    /// process' `cwd`) belongs to, and of the workspace root itself.
+    ///
+    /// Members are listed in `[workspace] members`, either literally or as glob patterns with `*`
+    /// and `?` (e.g. `crates/*` or `tools/cargo-*`). Members under a path in `[workspace] exclude`
+    /// are left out. If the manifest is not part of a workspace, no manifests are returned.
+    pub fn find_workspace_members(path: &Option<PathBuf>) -> Result<Vec<PathBuf>, Box<Error>> {
+        let manifest_path = find(path, CargoFile::Config)?.canonicalize()?;
+        let (root_path, root) = match Manifest::find_workspace_root(path)? {
+            Some(root) => root,
+            None => return Ok(Vec::new()),
+        };
+        let root_dir = root_path.parent().unwrap_or_else(|| Path::new("."));
+
+        let get_paths = |key| {
+            root.data
+                .get("workspace")
+                .and_then(|w| w.get(key))
+                .and_then(toml::Value::as_array)
+                .map(|paths| {
+                    paths
+                        .iter()
+                        .filter_map(toml::Value::as_str)
+                        .map(ToOwned::to_owned)
+                        .collect()
+                })
+                .unwrap_or_else(Vec::new)
+        };
+        let excluded: Vec<PathBuf> = get_paths("exclude")
+            .iter()
+            .map(|path| root_dir.join(path))
+            .collect();
+
+        let mut member_paths = vec![root_path.clone()];
+        for member in get_paths("members") {
+            member_paths.extend(
+                expand_member_glob(root_dir, &member)
+                    .into_iter()
+                    .filter(|member| !excluded.iter().any(|excluded| member.starts_with(excluded)))
+                    .map(|member| member.join(CargoFile::Config.name())),
+            );
+        }
+
+        Ok(member_paths
+            .into_iter()
+            .filter(|member| member.is_file())
+            .filter(|member| {
+                member
+                    .canonicalize()
+                    .map(|member| member != manifest_path)
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    /// Open the manifests of the other members of the workspace the manifest at `path` belongs
+    /// to, see `find_workspace_members`. Manifests that cannot be read are left out.
+    pub fn open_workspace_members(path: &Option<PathBuf>) -> Result<Vec<Manifest>, Box<Error>> {
+        Ok(Manifest::find_workspace_members(path)?
+            .into_iter()
+            .filter_map(|member| Manifest::open(&Some(member)).ok())
+            .filter(|member| member.data.contains_key("package"))
+            .collect())
+    }
+
     /// Overwrite a file with TOML data.
     pub fn write_to_file(&self, file: &mut File) -> Result<(), Box<Error>> {
         let mut toml = self.data.clone();
@@ -242,10 +577,13 @@ impl Manifest {
             .or_else(|| toml.remove("project").map(|data| ("project", data)))
             .ok_or(ManifestError::MissingManifest)?;
 
+        // The package goes first. It is written as a table of its own, so that the headers of its
+        // subtables like `[package.metadata.foo]` keep their full path.
+        let mut package = toml::value::Table::new();
+        package.insert(proj_header.to_owned(), proj_data);
         let new_contents = format!(
-            "[{}]\n{}{}",
-            proj_header,
-            proj_data,
+            "{}{}",
+            toml::Value::Table(package),
             toml::Value::Table(toml)
         );
         let new_contents_bytes = new_contents.as_bytes();
@@ -294,8 +632,285 @@ impl Manifest {
         Ok(())
     }
 
+    /// Whether Cargo accepts `dep:<name>` in `[features]` for this manifest.
+    ///
+    /// This is the case if the manifest already uses that syntax, or if it requires a Cargo
+    /// version that supports it (1.60 or newer) through `rust-version` or `edition`.
+    pub fn supports_namespaced_features(&self) -> bool {
+        let already_used = self.data
+            .get("features")
+            .and_then(toml::Value::as_table)
+            .into_iter()
+            .flat_map(|features| features.values())
+            .filter_map(toml::Value::as_array)
+            .flat_map(|values| values.iter())
+            .filter_map(toml::Value::as_str)
+            .any(|value| value.starts_with("dep:"));
+
+        let package = self.data.get("package").or_else(|| self.data.get("project"));
+        let rust_version = package
+            .and_then(|p| p.get("rust-version"))
+            .and_then(toml::Value::as_str)
+            .and_then(|version| {
+                let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+                match (parts.next(), parts.next()) {
+                    (Some(Some(major)), Some(Some(minor))) => Some((major, minor)),
+                    (Some(Some(major)), None) => Some((major, 0)),
+                    _ => None,
+                }
+            });
+        let edition = package
+            .and_then(|p| p.get("edition"))
+            .and_then(toml::Value::as_str)
+            .and_then(|edition| edition.parse::<u32>().ok());
+
+        already_used || rust_version.map_or(false, |version| version >= (1, 60)) ||
+            edition.map_or(false, |edition| edition >= 2024)
+    }
+
+    /// Find a feature that enables an optional dependency through `dep:<name>`.
+    ///
+    /// A dependency that is referred to like this has no implicit feature of its own name.
+    pub fn find_enabling_feature(&self, name: &str) -> Option<String> {
+        let value = format!("dep:{}", name);
+        self.data
+            .get("features")
+            .and_then(toml::Value::as_table)
+            .into_iter()
+            .flat_map(|features| features.iter())
+            .find(|&(_, values)| {
+                values
+                    .as_array()
+                    .map_or(false, |values| values.iter().any(|v| v.as_str() == Some(&value)))
+            })
+            .map(|(feature, _)| feature.clone())
+    }
+
+    /// Make the given features enable an (optional) dependency.
+    ///
+    /// Features that do not exist yet are created. The dependency is referred to as `dep:<name>`
+    /// if `supports_namespaced_features` allows it, and by its plain name otherwise.
+    pub fn add_to_features(
+        &mut self,
+        name: &str,
+        features: &[String],
+    ) -> Result<(), ManifestError> {
+        let value = if self.supports_namespaced_features() {
+            format!("dep:{}", name)
+        } else {
+            name.to_owned()
+        };
+
+        let table = self.get_table(&["features".to_owned()])?;
+        for feature in features {
+            if feature == &value {
+                return Err(ManifestError::FeatureNameConflict(feature.clone()));
+            }
+
+            let entry = table
+                .entry(feature.clone())
+                .or_insert_with(|| toml::Value::Array(Vec::new()));
+            match *entry {
+                toml::Value::Array(ref mut values) => {
+                    if !values.iter().any(|v| v.as_str() == Some(&value)) {
+                        values.push(toml::Value::String(value.clone()));
+                    }
+                }
+                _ => {
+                    return Err(ManifestError::NonExistentTable(
+                        format_table_path(&["features".to_owned(), feature.clone()]),
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove all references to a dependency from `[features]`.
+    ///
+    /// This removes `name` (unless there is a feature of that name), `dep:name`, `name/feature`
+    /// and `name?/feature`. If `remove_empty` is set, features that are left empty are removed,
+    /// along with any references to them by name. Returns the changes made.
+    pub fn remove_from_features(&mut self, name: &str, remove_empty: bool) -> Vec<FeatureChange> {
+        let mut changes = Vec::new();
+
+        {
+            let features = match self.data.get_mut("features") {
+                Some(&mut toml::Value::Table(ref mut features)) => features,
+                _ => return changes,
+            };
+
+            let is_feature = features.contains_key(name);
+            let refers_to = |value: &str, name: &str, is_dependency: bool| if is_dependency {
+                (value == name && !is_feature) || value == format!("dep:{}", name) ||
+                    value.starts_with(&format!("{}/", name)) ||
+                    value.starts_with(&format!("{}?/", name))
+            } else {
+                // Features are only enabled by their plain name, the other forms refer to a
+                // dependency that may share it.
+                value == name
+            };
+
+            // Features that are left empty are removed in turn, as are references to them.
+            let mut pending = vec![(name.to_owned(), true)];
+            while let Some((name, is_dependency)) = pending.pop() {
+                let mut emptied = Vec::new();
+
+                for (feature, values) in features.iter_mut() {
+                    let values = match *values {
+                        toml::Value::Array(ref mut values) => values,
+                        _ => continue,
+                    };
+                    let before = values.len();
+
+                    values.retain(|value| {
+                        let value = match value.as_str() {
+                            Some(value) => value,
+                            None => return true,
+                        };
+                        if refers_to(value, &name, is_dependency) {
+                            changes.push(FeatureChange::RemovedReference {
+                                feature: feature.clone(),
+                                value: value.to_owned(),
+                            });
+                            false
+                        } else {
+                            true
+                        }
+                    });
+
+                    if remove_empty && before > 0 && values.is_empty() {
+                        emptied.push(feature.clone());
+                    }
+                }
+
+                for feature in emptied {
+                    features.remove(&feature);
+                    changes.push(FeatureChange::RemovedFeature(feature.clone()));
+                    pending.push((feature, false));
+                }
+            }
+        }
+
+        if self.data
+            .get("features")
+            .and_then(toml::Value::as_table)
+            .map(|features| features.is_empty()) == Some(true)
+        {
+            self.data.remove("features");
+        }
+
+        changes
+    }
+
+    /// Get the paths of all dependency sections that have an entry for `name`.
+    pub fn find_dependency_sections(&self, name: &str) -> Vec<Vec<String>> {
+        self.get_sections()
+            .into_iter()
+            .filter(|&(_, ref table)| table.contains_key(name))
+            .map(|(table_path, _)| table_path)
+            .collect()
+    }
+
+    /// Get the paths of all dependency sections that depend on the crate `name`, along with the
+    /// key of its entry there.
+    ///
+    /// Unlike `find_dependency_sections`, entries renamed with `package = "name"` are found too.
+    pub fn find_dependency_entries(&self, name: &str) -> Vec<(Vec<String>, String)> {
+        self.get_sections()
+            .into_iter()
+            .filter_map(|(table_path, table)| {
+                table
+                    .iter()
+                    .find(|&(key, entry)| {
+                        key == name ||
+                            entry.get("package").and_then(toml::Value::as_str) == Some(name)
+                    })
+                    .map(|(key, _)| (table_path.clone(), key.clone()))
+            })
+            .collect()
+    }
+
+    /// Move an entry from one dependency section to another.
+    ///
+    /// The entry is moved as it is, with its version, source, features and any other keys. If the
+    /// old section is left empty, it is removed.
+    pub fn move_dependency(
+        &mut self,
+        from: &[String],
+        to: &[String],
+        name: &str,
+    ) -> Result<(), ManifestError> {
+        if self.get_sections()
+            .iter()
+            .any(|&(ref path, ref table)| path.as_slice() == to && table.contains_key(name))
+        {
+            return Err(ManifestError::DuplicateDependency(
+                name.into(),
+                format_table_path(to),
+            ));
+        }
+
+        let entry = self.remove_entry(from, name)?;
+        self.get_table(to)?.insert(name.into(), entry);
+
+        Ok(())
+    }
+
+    /// Remove an entry from the table at `table_path` and return it.
+    ///
+    /// Tables along the path that are left empty are removed as well.
+    fn remove_entry(
+        &mut self,
+        table_path: &[String],
+        name: &str,
+    ) -> Result<toml::Value, ManifestError> {
+        /// Descend into a manifest until the table containing the entry is found.
+        fn descend(
+            input: &mut BTreeMap<String, toml::Value>,
+            path: &[String],
+            full_path: &[String],
+            name: &str,
+        ) -> Result<toml::Value, ManifestError> {
+            let segment = match path.first() {
+                Some(segment) => segment,
+                None => {
+                    return input.remove(name).ok_or_else(|| {
+                        ManifestError::NonExistentDependency(
+                            name.into(),
+                            format_table_path(full_path),
+                        )
+                    })
+                }
+            };
+
+            let result = match input.get_mut(segment) {
+                Some(&mut toml::Value::Table(ref mut table)) => {
+                    descend(table, &path[1..], full_path, name)
+                }
+                _ => Err(ManifestError::NonExistentTable(format_table_path(full_path))),
+            };
+
+            if input
+                .get(segment)
+                .and_then(toml::Value::as_table)
+                .map(|table| table.is_empty()) == Some(true)
+            {
+                input.remove(segment);
+            }
+
+            result
+        }
+
+        descend(&mut self.data, table_path, table_path, name)
+    }
+
     /// Remove entry from a Cargo.toml.
     ///
+    /// The table is given as a path, e.g. `["target", "cfg(unix)", "dependencies"]`. Tables that
+    /// are left empty are removed.
+    ///
     /// # Examples
     ///
     /// ```
@@ -307,33 +922,19 @@ impl Manifest {
     ///
     ///     let mut manifest = Manifest { data: toml::value::Table::new() };
     ///     let dep = Dependency::new("cargo-edit").set_version("0.1.0");
-    ///     let _ = manifest.insert_into_table(&vec!["dependencies".to_owned()], &dep);
-    ///     assert!(manifest.remove_from_table("dependencies", &dep.name).is_ok());
-    ///     assert!(manifest.remove_from_table("dependencies", &dep.name).is_err());
+    ///     let table = vec!["dependencies".to_owned()];
+    ///     let _ = manifest.insert_into_table(&table, &dep);
+    ///     assert!(manifest.remove_from_table(&table, &dep.name).is_ok());
+    ///     assert!(manifest.remove_from_table(&table, &dep.name).is_err());
     ///     assert!(manifest.data.is_empty());
     /// # }
     /// ```
-    pub fn remove_from_table(&mut self, table: &str, name: &str) -> Result<(), ManifestError> {
-        let manifest = &mut self.data;
-        let entry = manifest.entry(String::from(table));
-
-        match entry {
-            Entry::Vacant(_) => Err(ManifestError::NonExistentTable(table.into())),
-            Entry::Occupied(mut section) => {
-                let result = match *section.get_mut() {
-                    toml::Value::Table(ref mut deps) => {
-                        deps.remove(name).map(|_| ()).ok_or_else(|| {
-                            ManifestError::NonExistentDependency(name.into(), table.into())
-                        })
-                    }
-                    _ => Err(ManifestError::NonExistentTable(table.into())),
-                };
-                if section.get().as_table().map(|x| x.is_empty()) == Some(true) {
-                    section.remove();
-                }
-                result
-            }
-        }
+    pub fn remove_from_table(
+        &mut self,
+        table_path: &[String],
+        name: &str,
+    ) -> Result<(), ManifestError> {
+        self.remove_entry(table_path, name).map(|_| ())
     }
 
     /// Add multiple dependencies to manifest
@@ -375,7 +976,7 @@ mod tests {
         let _ = manifest.insert_into_table(&["dependencies".to_owned()], &dep);
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&["dependencies".to_owned()], &dep.name)
                 .is_ok()
         );
         assert_eq!(manifest, clone);
@@ -416,6 +1017,408 @@ mod tests {
         assert_eq!(manifest, original);
     }
 
+    #[test]
+    fn get_locked_versions_newest_first() {
+        let lock: Manifest = r#"
+            [[package]]
+            name = "serde"
+            version = "0.9.15"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "serde"
+            version = "1.0.130"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "serde"
+            version = "2.0.0"
+            source = "git+https://github.com/serde-rs/serde#0123456789"
+
+            [[package]]
+            name = "regex"
+            version = "0.2.2"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+        "#.parse()
+            .unwrap();
+
+        assert_eq!(
+            lock.get_locked_versions("serde"),
+            vec![
+                semver::Version::parse("1.0.130").unwrap(),
+                semver::Version::parse("0.9.15").unwrap(),
+            ]
+        );
+        assert!(lock.get_locked_versions("toml").is_empty());
+    }
+
+    #[test]
+    fn get_requirement_from_any_section() {
+        let manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [dependencies]
+            local = { path = "../local", version = "0.1" }
+
+            [target.'cfg(unix)'.dev-dependencies]
+            serde_renamed = { package = "serde", version = "1.0" }
+        "#.parse()
+            .unwrap();
+
+        assert_eq!(manifest.get_requirement("serde"), Some("1.0".to_owned()));
+        assert_eq!(manifest.get_requirement("serde_renamed"), None);
+        assert_eq!(manifest.get_requirement("local"), None);
+    }
+
+    #[test]
+    fn get_all_sections_includes_overrides() {
+        let manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [dependencies]
+            serde = "1.0"
+
+            [workspace.dependencies]
+            log = "0.4"
+
+            [patch.crates-io]
+            rand = { path = "../rand" }
+
+            [patch.'https://github.com/example/repo']
+            other = "1.0"
+
+            [replace]
+            "libc:0.2.40" = { path = "../libc" }
+        "#.parse()
+            .unwrap();
+
+        let sections: Vec<_> = manifest
+            .get_all_sections()
+            .into_iter()
+            .map(|(path, _)| path.join("."))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                "dependencies",
+                "workspace.dependencies",
+                "patch.crates-io",
+                "patch.https://github.com/example/repo",
+                "replace",
+            ]
+        );
+        assert_eq!(manifest.get_sections().len(), 1);
+    }
+
+    #[test]
+    fn parses_replace_keys() {
+        assert_eq!(parse_replace_key("libc:0.2.40"), Some(("libc", "0.2.40")));
+        assert_eq!(
+            parse_replace_key("https://github.com/rust-lang/crates.io-index#libc:0.2.40"),
+            Some(("libc", "0.2.40"))
+        );
+        assert_eq!(parse_replace_key("libc"), None);
+        assert_eq!(parse_replace_key(":0.2.40"), None);
+    }
+
+    #[test]
+    fn get_tool_metadata_prefers_package() {
+        let manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [package.metadata.cargo-add]
+            prefer-locked = false
+
+            [workspace.metadata.cargo-add]
+            prefer-locked = true
+            other = "value"
+        "#.parse()
+            .unwrap();
+
+        let metadata = manifest.get_tool_metadata("cargo-add");
+        assert_eq!(metadata["prefer-locked"].as_bool(), Some(false));
+        assert_eq!(metadata["other"].as_str(), Some("value"));
+        assert!(manifest.get_tool_metadata("cargo-upgrade").is_empty());
+    }
+
+    #[test]
+    fn get_tool_metadata_from_workspace_root() {
+        let root: Manifest = r#"
+            [workspace]
+            members = ["foo"]
+
+            [workspace.metadata.cargo-upgrade]
+            ignore = ["openssl"]
+            patch-only = ["serde"]
+
+            [package.metadata.cargo-upgrade]
+            max = { tokio = "<2" }
+        "#.parse()
+            .unwrap();
+        let member: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [package.metadata.cargo-upgrade]
+            patch-only = []
+        "#.parse()
+            .unwrap();
+
+        let metadata = member.get_tool_metadata_in_workspace(Some(&root), "cargo-upgrade");
+        assert_eq!(metadata["ignore"][0].as_str(), Some("openssl"));
+        assert_eq!(metadata["patch-only"].as_array().map(Vec::len), Some(0));
+        // Only the root's workspace metadata applies to its members.
+        assert!(metadata.get("max").is_none());
+    }
+
+    #[test]
+    fn move_dependency_between_sections() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [dependencies]
+            bar = { version = "0.1", features = ["baz"], optional = true }
+        "#.parse()
+            .unwrap();
+        let from = vec!["dependencies".to_owned()];
+        let to = vec![
+            "target".to_owned(),
+            "cfg(unix)".to_owned(),
+            "build-dependencies".to_owned(),
+        ];
+
+        assert_eq!(manifest.find_dependency_sections("bar"), vec![from.clone()]);
+        manifest.move_dependency(&from, &to, "bar").unwrap();
+        assert_eq!(manifest.find_dependency_sections("bar"), vec![to.clone()]);
+        assert!(manifest.data.get("dependencies").is_none());
+
+        let entry = &manifest.data["target"]["cfg(unix)"]["build-dependencies"]["bar"];
+        assert_eq!(entry["version"].as_str(), Some("0.1"));
+        assert_eq!(entry["features"][0].as_str(), Some("baz"));
+        assert_eq!(entry["optional"].as_bool(), Some(true));
+
+        assert!(manifest.move_dependency(&from, &to, "bar").is_err());
+    }
+
+    #[test]
+    fn find_renamed_dependency_entries() {
+        let manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [dependencies]
+            bar = "0.1"
+
+            [dev-dependencies]
+            bar2 = { version = "0.2", package = "bar" }
+        "#.parse()
+            .unwrap();
+
+        assert_eq!(
+            manifest.find_dependency_entries("bar"),
+            vec![
+                (vec!["dev-dependencies".to_owned()], "bar2".to_owned()),
+                (vec!["dependencies".to_owned()], "bar".to_owned()),
+            ]
+        );
+        assert_eq!(
+            manifest.find_dependency_sections("bar"),
+            vec![vec!["dependencies".to_owned()]]
+        );
+    }
+
+    #[test]
+    fn add_dependency_to_features() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [features]
+            default = ["std"]
+            std = []
+        "#.parse()
+            .unwrap();
+
+        manifest
+            .add_to_features("serde", &["default".to_owned(), "serialize".to_owned()])
+            .unwrap();
+        manifest
+            .add_to_features("serde", &["serialize".to_owned()])
+            .unwrap();
+
+        let features = &manifest.data["features"];
+        assert_eq!(
+            features["default"],
+            toml::Value::Array(vec![
+                toml::Value::String("std".to_owned()),
+                toml::Value::String("serde".to_owned()),
+            ])
+        );
+        assert_eq!(
+            features["serialize"],
+            toml::Value::Array(vec![toml::Value::String("serde".to_owned())])
+        );
+        assert!(
+            manifest
+                .add_to_features("serde", &["serde".to_owned()])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn add_dependency_to_features_with_dep_syntax() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+            rust-version = "1.60"
+        "#.parse()
+            .unwrap();
+        assert!(manifest.supports_namespaced_features());
+
+        manifest
+            .add_to_features("serde", &["serde".to_owned()])
+            .unwrap();
+        manifest
+            .add_to_features("serde_json", &["json".to_owned()])
+            .unwrap();
+        assert_eq!(
+            manifest.data["features"]["serde"],
+            toml::Value::Array(vec![toml::Value::String("dep:serde".to_owned())])
+        );
+        assert_eq!(manifest.find_enabling_feature("serde_json"), Some("json".to_owned()));
+        assert_eq!(manifest.find_enabling_feature("log"), None);
+
+        let manifest: Manifest = r#"
+            [package]
+            name = "foo"
+            edition = "2021"
+            rust-version = "1.59.0"
+        "#.parse()
+            .unwrap();
+        assert!(!manifest.supports_namespaced_features());
+    }
+
+    #[test]
+    fn remove_dependency_from_features() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [features]
+            default = ["std", "serde"]
+            std = []
+            serde = ["dep:serde", "serde_json?/std"]
+            derive = ["serde/derive"]
+            all = ["derive", "std"]
+        "#.parse()
+            .unwrap();
+
+        let changes = manifest.remove_from_features("serde", true);
+        assert_eq!(
+            changes,
+            vec![
+                FeatureChange::RemovedReference {
+                    feature: "derive".to_owned(),
+                    value: "serde/derive".to_owned(),
+                },
+                FeatureChange::RemovedReference {
+                    feature: "serde".to_owned(),
+                    value: "dep:serde".to_owned(),
+                },
+                FeatureChange::RemovedFeature("derive".to_owned()),
+                FeatureChange::RemovedReference {
+                    feature: "all".to_owned(),
+                    value: "derive".to_owned(),
+                },
+            ]
+        );
+
+        // `serde` in `default` refers to the feature, which is still there
+        let features = &manifest.data["features"];
+        assert_eq!(features["default"].as_array().unwrap().len(), 2);
+        assert_eq!(features["serde"].as_array().unwrap().len(), 1);
+        assert_eq!(features["std"].as_array().unwrap().len(), 0);
+        assert!(features.get("derive").is_none());
+    }
+
+    #[test]
+    fn remove_emptied_feature_named_like_a_dependency() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [features]
+            tracing = ["dep:log"]
+            full = ["tracing", "dep:tracing", "tracing/std", "tracing?/log"]
+        "#.parse()
+            .unwrap();
+
+        let changes = manifest.remove_from_features("log", true);
+        assert_eq!(
+            changes,
+            vec![
+                FeatureChange::RemovedReference {
+                    feature: "tracing".to_owned(),
+                    value: "dep:log".to_owned(),
+                },
+                FeatureChange::RemovedFeature("tracing".to_owned()),
+                FeatureChange::RemovedReference {
+                    feature: "full".to_owned(),
+                    value: "tracing".to_owned(),
+                },
+            ]
+        );
+
+        // The other references are to the `tracing` dependency
+        assert_eq!(
+            manifest.data["features"]["full"],
+            toml::Value::Array(vec![
+                toml::Value::String("dep:tracing".to_owned()),
+                toml::Value::String("tracing/std".to_owned()),
+                toml::Value::String("tracing?/log".to_owned()),
+            ])
+        );
+    }
+
+    #[test]
+    fn remove_dependency_from_features_keeping_empty_ones() {
+        let mut manifest: Manifest = r#"
+            [package]
+            name = "foo"
+
+            [features]
+            default = ["serde"]
+        "#.parse()
+            .unwrap();
+
+        let changes = manifest.clone().remove_from_features("serde", true);
+        assert_eq!(changes.len(), 2);
+
+        let changes = manifest.remove_from_features("serde", false);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(manifest.data["features"]["default"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn format_table_paths() {
+        assert_eq!(
+            format_table_path(&["dev-dependencies".to_owned()]),
+            "dev-dependencies"
+        );
+        assert_eq!(
+            format_table_path(&[
+                "target".to_owned(),
+                "cfg(unix)".to_owned(),
+                "dependencies".to_owned(),
+            ]),
+            "target.'cfg(unix)'.dependencies"
+        );
+    }
+
     #[test]
     fn remove_dependency_no_section() {
         let mut manifest = Manifest {
@@ -424,7 +1427,7 @@ mod tests {
         let dep = Dependency::new("cargo-edit").set_version("0.1.0");
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&["dependencies".to_owned()], &dep.name)
                 .is_err()
         );
     }
@@ -439,7 +1442,7 @@ mod tests {
         let _ = manifest.insert_into_table(&["dependencies".to_owned()], &other_dep);
         assert!(
             manifest
-                .remove_from_table("dependencies", &dep.name)
+                .remove_from_table(&["dependencies".to_owned()], &dep.name)
                 .is_err()
         );
     }
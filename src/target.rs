@@ -0,0 +1,247 @@
+use std::env;
+use std::io;
+use std::process::Command;
+
+/// Enumeration of errors which can occur when validating a target platform.
+quick_error! {
+    #[derive(Debug)]
+    pub enum TargetError {
+        /// The target specification was empty.
+        Empty {
+            description("empty target")
+            display("Target specification may not be empty.")
+        }
+        /// The `cfg(...)` expression could not be parsed.
+        InvalidCfg(target: String, reason: String) {
+            description("invalid cfg expression")
+            display("The target `{}` is not a valid `cfg` expression: {}.", target, reason)
+        }
+        /// The target triple is not known to `rustc`.
+        UnknownTriple(target: String) {
+            description("unknown target triple")
+            display("The target `{}` is neither a `cfg(...)` expression nor a target triple \
+                     known to `rustc --print target-list`.", target)
+        }
+        /// `rustc` could not be queried for its list of targets.
+        Rustc(err: io::Error) {
+            from()
+            description("could not run rustc")
+            display("Could not query `rustc` for its list of targets: {}", err)
+            cause(err)
+        }
+    }
+}
+
+/// Check that `target` can be used as the `<target>` in a `[target.<target>.dependencies]` table.
+///
+/// A target is either a `cfg(...)` expression, a target triple known to `rustc`, or the path to
+/// a custom target specification (ending in `.json`).
+pub fn validate_target(target: &str) -> Result<(), TargetError> {
+    let target = target.trim();
+
+    if target.is_empty() {
+        Err(TargetError::Empty)
+    } else if target.starts_with("cfg(") {
+        CfgParser::new(target)
+            .parse()
+            .map_err(|reason| TargetError::InvalidCfg(target.into(), reason))
+    } else if target.ends_with(".json") {
+        Ok(())
+    } else if get_target_list()?.iter().any(|t| t == target) {
+        Ok(())
+    } else {
+        Err(TargetError::UnknownTriple(target.into()))
+    }
+}
+
+/// Ask `rustc` (or whatever `$RUSTC` points to) for the target triples it knows about.
+fn get_target_list() -> Result<Vec<String>, TargetError> {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let output = Command::new(rustc)
+        .args(&["--print", "target-list"])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(TargetError::Rustc(io::Error::new(
+            io::ErrorKind::Other,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_owned())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Recursive descent parser for the `cfg(...)` syntax Cargo accepts in target tables.
+///
+/// ```text
+/// cfg       := "cfg" "(" predicate ")"
+/// predicate := ident | ident "=" string | ("all" | "any") "(" list ")" | "not" "(" predicate ")"
+/// list      := [predicate ("," predicate)* [","]]
+/// ```
+struct CfgParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> CfgParser<'a> {
+    fn new(input: &'a str) -> CfgParser<'a> {
+        CfgParser { input: input, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<(), String> {
+        self.expect_ident("cfg")?;
+        self.expect('(')?;
+        self.predicate()?;
+        self.expect(')')?;
+        self.skip_whitespace();
+
+        if self.pos < self.input.len() {
+            Err(format!("unexpected `{}` after the expression", &self.input[self.pos..]))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn predicate(&mut self) -> Result<(), String> {
+        let ident = self.ident()?;
+
+        match ident.as_ref() {
+            "all" | "any" if self.peek() == Some('(') => {
+                self.expect('(')?;
+                while self.peek() != Some(')') {
+                    self.predicate()?;
+                    if self.peek() == Some(',') {
+                        self.expect(',')?;
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(')')
+            }
+            "not" if self.peek() == Some('(') => {
+                self.expect('(')?;
+                self.predicate()?;
+                self.expect(')')
+            }
+            _ if self.peek() == Some('=') => {
+                self.expect('=')?;
+                self.string()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        for (i, c) in self.input[start..].char_indices() {
+            let valid = if i == 0 {
+                c.is_alphabetic() || c == '_'
+            } else {
+                c.is_alphanumeric() || c == '_'
+            };
+            if !valid {
+                break;
+            }
+            self.pos = start + i + c.len_utf8();
+        }
+
+        if self.pos == start {
+            Err(format!("expected an identifier at position {}", start + 1))
+        } else {
+            Ok(self.input[start..self.pos].to_owned())
+        }
+    }
+
+    fn expect_ident(&mut self, expected: &str) -> Result<(), String> {
+        let ident = self.ident()?;
+        if ident == expected {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`, found `{}`", expected, ident))
+        }
+    }
+
+    fn string(&mut self) -> Result<(), String> {
+        self.expect('"')?;
+        match self.input[self.pos..].find('"') {
+            Some(end) => {
+                self.pos += end + 1;
+                Ok(())
+            }
+            None => Err("unterminated string".to_owned()),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+            None => Err(format!("expected `{}`, found the end of the expression", expected)),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_left().len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_cfg_expressions() {
+        for target in &[
+            "cfg(unix)",
+            "cfg(windows)",
+            r#"cfg(target_os = "macos")"#,
+            r#"cfg(all(unix, target_pointer_width = "64"))"#,
+            r#"cfg(any(windows, not(target_arch = "x86_64"),))"#,
+            "cfg( not( unix ) )",
+        ] {
+            assert!(validate_target(target).is_ok(), "{} should be valid", target);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_cfg_expressions() {
+        for target in &[
+            "cfg(unix",
+            "cfg()",
+            r#"cfg(target_os = macos)"#,
+            r#"cfg(target_os = "macos)"#,
+            "cfg(all(unix windows))",
+            "cfg(unix) extra",
+        ] {
+            assert!(validate_target(target).is_err(), "{} should be invalid", target);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_target() {
+        match validate_target("  ") {
+            Err(TargetError::Empty) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_custom_target_specification() {
+        assert!(validate_target("x86_64/windows.json").is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+use manifest::Manifest;
+use regex::{self, Regex};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml;
+
+/// How a dependency is used by the sources of a crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    /// The dependency is not referenced anywhere it could be used from
+    Unused,
+    /// A normal dependency that is only referenced by tests, benches or examples
+    OnlyDev,
+    /// A normal dependency that is only referenced by the build script
+    OnlyBuild,
+}
+
+/// A dependency that is not used (the way its section suggests)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedDependency {
+    /// The section the dependency is in
+    pub section: Vec<String>,
+    /// The name of the dependency, as it is written in the section
+    pub name: String,
+    /// How the dependency is used instead
+    pub usage: Usage,
+}
+
+/// The part of a crate a source file belongs to, which decides the dependencies it can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceKind {
+    /// Library and binaries
+    Main,
+    /// Tests, benches and examples
+    Dev,
+    /// The build script
+    Build,
+}
+
+/// Find the dependencies of a manifest that its crate's sources never reference.
+///
+/// This is a purely static check: the sources in `src/`, `tests/`, `benches/` and `examples/`, the
+/// build script, and any other target paths from the manifest are searched for `extern crate`,
+/// `use` and path references (`name::`) to each dependency. Normal dependencies can be used from
+/// everywhere but the build script, development dependencies from everywhere but the build
+/// script, and build dependencies from the build script only. Source files that are not UTF-8 are
+/// skipped with a warning.
+///
+/// `crate_root` is the directory the manifest is in.
+pub fn find_unused_dependencies(
+    manifest: &Manifest,
+    crate_root: &Path,
+) -> Result<Vec<UnusedDependency>, io::Error> {
+    let sources = read_sources(manifest, crate_root)?;
+    let mut unused = Vec::new();
+
+    for (section, table) in manifest.get_sections() {
+        let kind = section.last().cloned().unwrap_or_default();
+
+        for name in table.keys() {
+            let matcher = reference_matcher(name);
+            let used_by = |wanted: SourceKind| {
+                sources
+                    .iter()
+                    .any(|&(kind, ref source)| kind == wanted && matcher.is_match(source))
+            };
+
+            let usage = match kind.as_ref() {
+                "build-dependencies" if !used_by(SourceKind::Build) => Some(Usage::Unused),
+                "dev-dependencies" if !used_by(SourceKind::Main) && !used_by(SourceKind::Dev) => {
+                    Some(Usage::Unused)
+                }
+                "dependencies" if !used_by(SourceKind::Main) => {
+                    if used_by(SourceKind::Dev) {
+                        Some(Usage::OnlyDev)
+                    } else if used_by(SourceKind::Build) {
+                        Some(Usage::OnlyBuild)
+                    } else {
+                        Some(Usage::Unused)
+                    }
+                }
+                _ => None,
+            };
+
+            if let Some(usage) = usage {
+                unused.push(UnusedDependency {
+                    section: section.clone(),
+                    name: name.clone(),
+                    usage: usage,
+                });
+            }
+        }
+    }
+
+    Ok(unused)
+}
+
+/// Build a regex matching references to a dependency in Rust source code.
+fn reference_matcher(name: &str) -> Regex {
+    let ident = regex::escape(&name.replace('-', "_"));
+    Regex::new(&format!(
+        r"(\bextern\s+crate\s+{ident}\b)|(\buse\s+(::)?{ident}\b)|((^|[^\w:])(::)?{ident}\s*::)",
+        ident = ident
+    )).unwrap()
+}
+
+/// Read all source files of a crate, along with the part of the crate they belong to.
+fn read_sources(
+    manifest: &Manifest,
+    crate_root: &Path,
+) -> Result<Vec<(SourceKind, String)>, io::Error> {
+    let mut paths: Vec<(SourceKind, PathBuf)> = vec![
+        (SourceKind::Main, crate_root.join("src")),
+        (SourceKind::Dev, crate_root.join("tests")),
+        (SourceKind::Dev, crate_root.join("benches")),
+        (SourceKind::Dev, crate_root.join("examples")),
+    ];
+
+    let package = manifest
+        .data
+        .get("package")
+        .or_else(|| manifest.data.get("project"));
+    let build_script = match package.and_then(|p| p.get("build")) {
+        Some(&toml::Value::String(ref build)) => Some(crate_root.join(build)),
+        Some(&toml::Value::Boolean(false)) => None,
+        _ => Some(crate_root.join("build.rs")),
+    };
+    if let Some(build_script) = build_script {
+        paths.push((SourceKind::Build, build_script));
+    }
+
+    // Targets can live outside of the default directories.
+    for &(target, kind) in &[
+        ("lib", SourceKind::Main),
+        ("bin", SourceKind::Main),
+        ("test", SourceKind::Dev),
+        ("bench", SourceKind::Dev),
+        ("example", SourceKind::Dev),
+    ] {
+        let targets = match manifest.data.get(target) {
+            Some(&toml::Value::Table(ref table)) => vec![table.clone()],
+            Some(&toml::Value::Array(ref tables)) => tables
+                .iter()
+                .filter_map(toml::Value::as_table)
+                .cloned()
+                .collect(),
+            _ => vec![],
+        };
+        for table in targets {
+            if let Some(path) = table.get("path").and_then(toml::Value::as_str) {
+                paths.push((kind, crate_root.join(path)));
+            }
+        }
+    }
+
+    let mut sources = Vec::new();
+    for (kind, path) in paths {
+        for file in find_rust_files(&path)? {
+            // The build script must not count as the library just because it is in `src/`.
+            let kind = if file.file_name() == Some("build.rs".as_ref()) &&
+                file.parent() == Some(crate_root)
+            {
+                SourceKind::Build
+            } else {
+                kind
+            };
+
+            let mut source = Vec::new();
+            File::open(&file)?.read_to_end(&mut source)?;
+            match String::from_utf8(source) {
+                Ok(source) => sources.push((kind, source)),
+                Err(_) => writeln!(
+                    io::stderr(),
+                    "WARN: Skipping `{}`, which is not UTF-8",
+                    file.display()
+                )?,
+            }
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Find all Rust source files at a path, recursively. Paths that do not exist are ignored.
+fn find_rust_files(path: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    if metadata.is_file() {
+        let is_rust = path.extension().map_or(false, |ext| ext == "rs");
+        return Ok(if is_rust { vec![path.to_owned()] } else { vec![] });
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        files.extend(find_rust_files(&entry?.path())?);
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_references() {
+        let matcher = reference_matcher("serde-json");
+        for source in &[
+            "extern crate serde_json;",
+            "#[macro_use]\nextern crate serde_json as json;",
+            "use serde_json;",
+            "use serde_json::{self, Value};",
+            "use ::serde_json::Value;",
+            "let v: serde_json::Value = serde_json :: from_str(s)?;",
+            "fn f() -> ::serde_json::Value {",
+        ] {
+            assert!(matcher.is_match(source), "{} should match", source);
+        }
+
+        for source in &[
+            "use my_serde_json::Value;",
+            "let serde_json = 1;",
+            "use foo::serde_json::Value;",
+            "// serde_json",
+        ] {
+            assert!(!matcher.is_match(source), "{} should not match", source);
+        }
+    }
+}
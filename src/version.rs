@@ -0,0 +1,243 @@
+use semver::{Version, VersionReq};
+use std::fmt;
+
+/// How much a version requirement changed, judged by the lowest version it allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// The requirement is the same
+    Unchanged,
+    /// Only the patch version (or the prerelease) changed
+    Patch,
+    /// The minor version changed
+    Minor,
+    /// The major version changed
+    Major,
+    /// One of the requirements is not a plain version, so they cannot be compared
+    Unknown,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match *self {
+            ChangeKind::Unchanged => "unchanged",
+            ChangeKind::Patch => "patch",
+            ChangeKind::Minor => "minor",
+            ChangeKind::Major => "major",
+            ChangeKind::Unknown => "unknown",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// Classify the change from one version requirement to another, e.g. `0.6` to `0.8.1` is a
+/// minor change.
+pub fn classify_change(old: &str, new: &str) -> ChangeKind {
+    if old.trim() == new.trim() {
+        return ChangeKind::Unchanged;
+    }
+
+    match (lowest_version(old), lowest_version(new)) {
+        (Some(old), Some(new)) => if old.major != new.major {
+            ChangeKind::Major
+        } else if old.minor != new.minor {
+            ChangeKind::Minor
+        } else if old.patch != new.patch || old.pre != new.pre {
+            ChangeKind::Patch
+        } else {
+            ChangeKind::Unchanged
+        },
+        _ => ChangeKind::Unknown,
+    }
+}
+
+/// Whether a version requirement pins an exact version, like `=0.15.1`
+pub fn is_pinned(req: &str) -> bool {
+    let req = req.trim();
+    req.starts_with('=') && !req.contains(',')
+}
+
+/// Rewrite a version requirement to start at `latest`, keeping its operator and the number of
+/// version components it was written with, e.g. `~1.2` and `1.4.3` give `~1.4`.
+///
+/// Wildcards are kept in place (`1.*` gives `2.*`). If `latest` is a prerelease, it is written
+/// with all of its components, since a shorter requirement would not match it. Returns `None`
+/// for requirements that cannot be moved to a newer version without changing their meaning:
+/// those with several comparators or an upper bound (`<`, `<=`, `>`).
+pub fn upgrade_requirement(old: &str, latest: &str) -> Option<String> {
+    let latest_version = match Version::parse(latest) {
+        Ok(version) => version,
+        // Nothing sensible can be kept, use the new version as it is.
+        Err(_) => return Some(latest.to_owned()),
+    };
+
+    let old = old.trim();
+    if old.contains(',') {
+        return None;
+    }
+    let operator_length = old.find(|c| !"=^~><".contains(c)).unwrap_or(old.len());
+    let (operator, version) = old.split_at(operator_length);
+    if !["", "=", "^", "~", ">="].contains(&operator) {
+        return None;
+    }
+
+    let components: Vec<&str> = version
+        .trim()
+        .split(|c| c == '-' || c == '+')
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .collect();
+    if latest_version.is_prerelease() || components.len() > 3 {
+        return Some(format!("{}{}", operator, latest));
+    }
+
+    let latest_components = [
+        latest_version.major.to_string(),
+        latest_version.minor.to_string(),
+        latest_version.patch.to_string(),
+    ];
+    let mut wildcard = false;
+    let new_components: Vec<String> = components
+        .iter()
+        .zip(latest_components.iter())
+        .map(|(&component, latest)| {
+            wildcard = wildcard || ["*", "x", "X"].contains(&component);
+            if wildcard {
+                component.to_owned()
+            } else {
+                latest.clone()
+            }
+        })
+        .collect();
+
+    Some(format!("{}{}", operator, new_components.join(".")))
+}
+
+/// Whether a version is semver compatible with the lowest version a requirement allows, e.g.
+/// `1.4.0` is compatible with `~1.2`, but `2.0.0` is not, and neither is `0.4.0` with `0.3`.
+pub fn is_compatible(req: &str, version: &str) -> bool {
+    let version = match Version::parse(version) {
+        Ok(version) => version,
+        Err(_) => return false,
+    };
+
+    lowest_version(req)
+        .and_then(|lowest| VersionReq::parse(&format!("^{}", lowest)).ok())
+        .map_or(false, |range| range.matches(&version))
+}
+
+/// Pick the version a requirement is resolved to from the versions of a crate in `Cargo.lock`
+/// (newest first): the newest one matching the requirement, or else the newest one that is
+/// semver compatible with it.
+pub fn select_locked_version<'a>(req: &str, locked: &'a [Version]) -> Option<&'a Version> {
+    let matching = VersionReq::parse(req)
+        .ok()
+        .and_then(|req| locked.iter().find(|version| req.matches(version)));
+
+    matching.or_else(|| {
+        locked
+            .iter()
+            .find(|version| is_compatible(req, &version.to_string()))
+    })
+}
+
+/// The version a requirement like `~1.2` (or a tag like `v1.2.0`) starts at, with missing
+/// components set to zero
+pub fn lowest_version(req: &str) -> Option<Version> {
+    let version = req.split(',')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .trim_left_matches(|c| "=^~><v".contains(c))
+        .trim();
+
+    let (numbers, rest) = match version.find(|c| c == '-' || c == '+') {
+        Some(index) => version.split_at(index),
+        None => (version, ""),
+    };
+    let mut components: Vec<&str> = numbers.split('.').collect();
+    if components.len() > 3 || components.iter().any(|c| c.parse::<u64>().is_err()) {
+        return None;
+    }
+    while components.len() < 3 {
+        components.push("0");
+    }
+
+    Version::parse(&format!("{}{}", components.join("."), rest)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_changes() {
+        assert_eq!(classify_change("0.6", "0.8.1"), ChangeKind::Minor);
+        assert_eq!(classify_change("^1.2.3", "1.2.4"), ChangeKind::Patch);
+        assert_eq!(classify_change("~1", "2.0.0"), ChangeKind::Major);
+        assert_eq!(classify_change(">=0.1, <0.3", "0.1.0"), ChangeKind::Unchanged);
+        assert_eq!(classify_change("0.6", "0.6.0"), ChangeKind::Unchanged);
+        assert_eq!(classify_change("1.0.0-alpha", "1.0.0"), ChangeKind::Patch);
+        assert_eq!(classify_change("1.0", "1.0"), ChangeKind::Unchanged);
+        assert_eq!(classify_change("v1.2.0", "v1.10.0"), ChangeKind::Minor);
+        assert_eq!(classify_change("*", "1.0.0"), ChangeKind::Unknown);
+        assert_eq!(classify_change("0.1", "foo--CURRENT_VERSION_TEST"), ChangeKind::Unknown);
+    }
+
+    #[test]
+    fn upgrades_keep_operator_and_precision() {
+        let upgrade = |old, latest| upgrade_requirement(old, latest).unwrap();
+        assert_eq!(upgrade("=0.15.1", "0.16.0"), "=0.16.0");
+        assert_eq!(upgrade("~1.2", "1.4.3"), "~1.4");
+        assert_eq!(upgrade("1.0", "1.0.117"), "1.0");
+        assert_eq!(upgrade("^0.3", "1.2.3"), "^1.2");
+        assert_eq!(upgrade(">= 1", "2.0.1"), ">=2");
+        assert_eq!(upgrade("0.1.1", "0.2.0"), "0.2.0");
+        assert_eq!(upgrade("1.*", "2.3.4"), "2.*");
+        assert_eq!(upgrade("*", "2.3.4"), "*");
+        assert_eq!(upgrade("0.9", "1.0.0-beta.2"), "1.0.0-beta.2");
+        assert_eq!(upgrade("0.1", "foo--CURRENT_VERSION_TEST"), "foo--CURRENT_VERSION_TEST");
+    }
+
+    #[test]
+    fn upgrades_keep_bounded_requirements() {
+        assert_eq!(upgrade_requirement(">=1.0, <1.5", "1.6.0"), None);
+        assert_eq!(upgrade_requirement("<2", "2.1.0"), None);
+    }
+
+    #[test]
+    fn checks_compatibility() {
+        assert!(is_compatible("~1.2", "1.4.0"));
+        assert!(is_compatible("=1.2.3", "1.2.9"));
+        assert!(!is_compatible("~1.2", "2.0.0"));
+        assert!(is_compatible("0.3", "0.3.7"));
+        assert!(!is_compatible("0.3", "0.4.0"));
+        assert!(!is_compatible("0.3", "foo--CURRENT_VERSION_TEST"));
+        assert!(!is_compatible("*", "1.0.0"));
+    }
+
+    #[test]
+    fn selects_locked_versions() {
+        let locked: Vec<Version> = ["2.0.1", "1.4.2", "1.2.7"]
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect();
+        let select = |req| select_locked_version(req, &locked).map(|v| v.to_string());
+
+        assert_eq!(select("1.2"), Some("1.4.2".to_owned()));
+        assert_eq!(select("~1.2"), Some("1.2.7".to_owned()));
+        assert_eq!(select(">=1"), Some("2.0.1".to_owned()));
+        assert_eq!(select("2"), Some("2.0.1".to_owned()));
+        assert_eq!(select("~1.3"), Some("1.4.2".to_owned()));
+        assert_eq!(select("3"), None);
+    }
+
+    #[test]
+    fn detects_pinned_requirements() {
+        assert!(is_pinned("=0.15.1"));
+        assert!(is_pinned(" = 1.0"));
+        assert!(!is_pinned("0.15.1"));
+        assert!(!is_pinned(">=0.15.1"));
+    }
+}
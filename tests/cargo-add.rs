@@ -4,9 +4,11 @@ extern crate pretty_assertions;
 extern crate tempdir;
 extern crate toml;
 
+use std::fs;
+use std::io::{Read, Write};
 use std::process;
 mod utils;
-use utils::{clone_out_test, execute_command, get_toml};
+use utils::{clone_out_test, clone_out_test_dir, execute_command, get_toml};
 
 /// Check 'failure' deps are not present
 fn no_manifest_failures(manifest: &toml::Value) -> bool {
@@ -215,6 +217,72 @@ fn adds_specified_version_with_inline_notation() {
     assert_eq!(val.as_str().expect("not string"), ">=0.1.1");
 }
 
+#[test]
+fn adds_specified_version_with_upgrade_method() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "versioned-package@0.1.1", "--upgrade=patch"],
+        &manifest,
+    );
+    execute_command(
+        &[
+            "add",
+            "versioned-package-2",
+            "--vers",
+            "0.2.3",
+            "--upgrade=none",
+        ],
+        &manifest,
+    );
+
+    // dependencies present afterwards, with the requested operator
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["versioned-package"];
+    assert_eq!(val.as_str().expect("not string"), "~0.1.1");
+    let val = &toml["dependencies"]["versioned-package-2"];
+    assert_eq!(val.as_str().expect("not string"), "=0.2.3");
+}
+
+#[test]
+fn adds_specified_version_with_explicit_operator() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "versioned-package@=1.2.3"], &manifest);
+    execute_command(
+        &["add", "versioned-package-2@~0.3", "--upgrade=patch"],
+        &manifest,
+    );
+    execute_command(
+        &["add", "versioned-package-3", "--vers", "^0.4", "--upgrade=none"],
+        &manifest,
+    );
+
+    // the operators given are kept, and `--upgrade` does not add another one
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["versioned-package"];
+    assert_eq!(val.as_str().expect("not string"), "=1.2.3");
+    let val = &toml["dependencies"]["versioned-package-2"];
+    assert_eq!(val.as_str().expect("not string"), "~0.3");
+    let val = &toml["dependencies"]["versioned-package-3"];
+    assert_eq!(val.as_str().expect("not string"), "^0.4");
+}
+
+#[test]
+fn fails_to_add_dependency_with_invalid_date() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--before", "2024-02-30T12:00"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(get_toml(&manifest).get("dependencies").is_none());
+}
+
 #[test]
 fn adds_multiple_dependencies_with_versions() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -450,6 +518,45 @@ fn adds_optional_dependency() {
     assert_eq!(val.as_bool().expect("optional not a bool"), true);
 }
 
+#[test]
+fn adds_optional_dependency_to_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &[
+            "add",
+            "versioned-package",
+            "--vers",
+            ">=0.1.1",
+            "--optional",
+            "--in-feature",
+            "default,extra",
+        ],
+        &manifest,
+    );
+
+    // dependency present afterwards, enabled by the features
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["versioned-package"]["optional"];
+    assert_eq!(val.as_bool().expect("optional not a bool"), true);
+    for feature in &["default", "extra"] {
+        let val = &toml["features"][feature];
+        assert_eq!(val.as_array().expect("feature not an array").len(), 1);
+        assert_eq!(val[0].as_str().unwrap(), "versioned-package");
+    }
+
+    // `--in-feature` needs `--optional`
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "failure", "--in-feature", "extra"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(no_manifest_failures(&get_toml(&manifest)));
+}
+
 #[test]
 fn adds_multiple_optional_dependencies() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -473,6 +580,139 @@ fn adds_multiple_optional_dependencies() {
         .expect("optional not a bool"));
 }
 
+#[test]
+fn adds_dependency_with_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // dependency not present beforehand
+    let toml = get_toml(&manifest);
+    assert!(toml.get("dependencies").is_none());
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--features", "serde"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    // activated and available features are listed
+    assert!(call.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&call.stdout),
+        "`my-package` vmy-package--CURRENT_VERSION_TEST features:
+    + serde
+    + std
+    - log
+"
+    );
+
+    // dependency present afterwards, with the feature activated
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(
+        val["version"].as_str().unwrap(),
+        "my-package--CURRENT_VERSION_TEST"
+    );
+    assert_eq!(val["features"].as_array().unwrap().len(), 1);
+    assert_eq!(val["features"][0].as_str().unwrap(), "serde");
+}
+
+#[test]
+fn adds_specified_version_with_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(
+        &["add", "my-package", "--vers", "0.4.2", "--features", "serde, std"],
+        &manifest,
+    );
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val["version"].as_str().unwrap(), "0.4.2");
+    let features: Vec<_> = val["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feature| feature.as_str().unwrap())
+        .collect();
+    assert_eq!(features, vec!["serde", "std"]);
+}
+
+#[test]
+fn adds_dependency_with_optional_dependency_as_feature() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    execute_command(&["add", "my-package", "--features", "log"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["my-package"];
+    assert_eq!(val["features"][0].as_str().unwrap(), "log");
+}
+
+#[test]
+fn fails_to_add_dependency_with_unknown_feature() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "my-package", "--features", "sedre"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(!call.status.success());
+    assert!(
+        String::from_utf8_lossy(&call.stderr)
+            .contains("has no feature `sedre`. Did you mean `serde`?")
+    );
+
+    // dependency not present afterwards
+    let toml = get_toml(&manifest);
+    assert!(toml.get("dependencies").is_none());
+}
+
+#[test]
+fn adds_dependency_with_version_from_lock_file() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let mut lock = fs::File::create(tmpdir.path().join("Cargo.lock")).unwrap();
+    lock.write_all(
+        br#"
+[[package]]
+name = "locked-package"
+version = "1.0.130"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+    ).unwrap();
+
+    execute_command(&["add", "locked-package", "--prefer-locked"], &manifest);
+    execute_command(&["add", "new-package", "--prefer-locked"], &manifest);
+
+    // the locked version is reused, new crates are looked up in the registry
+    let toml = get_toml(&manifest);
+    let val = &toml["dependencies"]["locked-package"];
+    assert_eq!(val.as_str().unwrap(), "1.0.130");
+    let val = &toml["dependencies"]["new-package"];
+    assert_eq!(val.as_str().unwrap(), "new-package--CURRENT_VERSION_TEST");
+}
+
+#[test]
+fn adds_dependency_with_requirement_from_workspace() {
+    let (tmpdir, _) = clone_out_test_dir("tests/fixtures/add/workspace");
+    let manifest = tmpdir.path().join("crates/app-cli/Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    // enabled through the workspace root's `[workspace.metadata.cargo-add]`, without the flag
+    execute_command(&["add", "shared-package"], manifest);
+    // `crates/app-old` matches `crates/app-*`, but is excluded from the workspace
+    execute_command(&["add", "old-package"], manifest);
+
+    let toml = get_toml(manifest);
+    let val = &toml["dependencies"]["shared-package"];
+    assert_eq!(val.as_str().unwrap(), "0.3");
+    let val = &toml["dependencies"]["old-package"];
+    assert_eq!(val.as_str().unwrap(), "old-package--CURRENT_VERSION_TEST");
+}
+
 #[test]
 fn adds_dependency_with_target_triple() {
     let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
@@ -533,6 +773,80 @@ fn adds_dependency_with_custom_target() {
 }
 
 
+#[test]
+fn adds_dev_build_dependency_with_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // dependencies not present beforehand
+    let toml = get_toml(&manifest);
+    assert!(toml.get("target").is_none());
+
+    execute_command(
+        &["add", "--dev", "--target", "cfg(unix)", "my-dev-package"],
+        &manifest,
+    );
+    execute_command(
+        &[
+            "add",
+            "--build",
+            "--target",
+            "x86_64-unknown-linux-gnu",
+            "my-build-package",
+        ],
+        &manifest,
+    );
+
+    // dependencies present afterwards, in the target-specific sections
+    let toml = get_toml(&manifest);
+    let val = &toml["target"]["cfg(unix)"]["dev-dependencies"]["my-dev-package"];
+    assert_eq!(
+        val.as_str().unwrap(),
+        "my-dev-package--CURRENT_VERSION_TEST"
+    );
+    let val = &toml["target"]["x86_64-unknown-linux-gnu"]["build-dependencies"]["my-build-package"];
+    assert_eq!(
+        val.as_str().unwrap(),
+        "my-build-package--CURRENT_VERSION_TEST"
+    );
+    assert!(toml.get("dev-dependencies").is_none());
+    assert!(toml.get("build-dependencies").is_none());
+}
+
+#[test]
+fn fails_to_add_dependency_with_invalid_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package1",
+        "--target",
+        "cfg(unix",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: The target `cfg(unix` is not a valid `cfg` \
+             expression: expected `)`, found the end of the expression.",
+        )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package1",
+        "--target",
+        "not-a-real-target",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: The target `not-a-real-target` is neither a \
+             `cfg(...)` expression nor a target triple known to `rustc --print target-list`.",
+        )
+        .unwrap();
+
+    assert!(get_toml(&manifest).get("target").is_none());
+}
+
 #[test]
 #[cfg(feature = "test-external-apis")]
 fn adds_dependency_normalized_name() {
@@ -723,6 +1037,240 @@ fn overwrite_path_with_version() {
     )
 }
 
+#[test]
+fn warns_about_dependency_in_other_section() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "0.1.1"], &manifest);
+
+    let call = process::Command::new("target/debug/cargo-add")
+        .args(&["add", "versioned-package", "--dev"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .env("CARGO_IS_TEST", "1")
+        .output()
+        .unwrap();
+
+    assert!(call.status.success());
+    assert!(String::from_utf8_lossy(&call.stdout).contains(
+        "WARN: `versioned-package` is also a dependency in `dependencies`. Use `--move` to \
+         move it instead.",
+    ));
+}
+
+#[test]
+fn moves_dependency_to_dev_dependencies() {
+    overwite_dependency_test(
+        &[
+            "add",
+            "versioned-package",
+            "--vers",
+            "0.1.1",
+            "--optional",
+            "--features",
+            "serde",
+        ],
+        &["add", "versioned-package", "--dev", "--move"],
+        r#"
+            [dev-dependencies.versioned-package]
+            version = "0.1.1"
+            features = ["serde"]
+        "#,
+    )
+}
+
+#[test]
+fn moves_dependency_to_target_build_dependencies() {
+    overwite_dependency_test(
+        &[
+            "add",
+            "versioned-package",
+            "--git",
+            "git://git.git",
+            "--optional",
+        ],
+        &[
+            "add",
+            "versioned-package",
+            "--build",
+            "--target",
+            "cfg(unix)",
+            "--move",
+        ],
+        r#"
+            [target.'cfg(unix)'.build-dependencies.versioned-package]
+            git = "git://git.git"
+            optional = true
+        "#,
+    )
+}
+
+#[test]
+fn moves_dependency_with_optional_and_features() {
+    overwite_dependency_test(
+        &[
+            "add",
+            "versioned-package",
+            "--build",
+            "--vers",
+            "~0.1.1",
+            "--features",
+            "serde",
+        ],
+        &[
+            "add",
+            "versioned-package",
+            "--move",
+            "--optional",
+            "--features",
+            "std serde",
+        ],
+        r#"
+            [dependencies.versioned-package]
+            version = "~0.1.1"
+            features = ["serde", "std"]
+            optional = true
+        "#,
+    )
+}
+
+#[test]
+fn moves_renamed_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.renamed");
+
+    execute_command(&["add", "original", "--dev", "--move"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml.get("dependencies").is_none());
+    let val = &toml["dev-dependencies"]["renamed"];
+    assert_eq!(val["version"].as_str(), Some("0.1"));
+    assert_eq!(val["package"].as_str(), Some("original"));
+}
+
+#[test]
+fn fails_to_move_dependency_with_version_or_extern() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "0.1.1"], &manifest);
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "versioned-package@0.2",
+        "--dev",
+        "--move",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--move` keeps the version and source of a \
+             dependency, they cannot be given with it.",
+        )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "versioned-package",
+        "--build",
+        "--move",
+        "--extern",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--move` cannot be combined with \
+             `--in-feature`, `--extern` or `--macro-use`.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn fails_to_move_missing_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "versioned-package",
+        "--dev",
+        "--move",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: The dependency `versioned-package` could \
+             not be found in any other section.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn adds_extern_crate_declarations() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    let lib = tmpdir.path().join("src").join("lib.rs");
+    fs::create_dir_all(lib.parent().unwrap()).unwrap();
+    fs::File::create(&lib)
+        .unwrap()
+        .write_all(b"//! A crate\n\n#![deny(missing_docs)]\n\nmod fetch;\n")
+        .unwrap();
+
+    execute_command(&["add", "my-package", "--extern"], &manifest);
+    execute_command(
+        &["add", "lazy-static", "--optional", "--macro-use"],
+        &manifest,
+    );
+    execute_command(
+        &["add", "serde-json", "--optional", "--in-feature", "json", "--extern"],
+        &manifest,
+    );
+    // Already declared, nothing changes
+    execute_command(&["add", "my-package", "--extern"], &manifest);
+
+    let mut source = String::new();
+    fs::File::open(&lib)
+        .unwrap()
+        .read_to_string(&mut source)
+        .unwrap();
+    assert_eq!(
+        source,
+        r#"//! A crate
+
+#![deny(missing_docs)]
+
+extern crate my_package;
+#[cfg(feature = "lazy-static")]
+#[macro_use]
+extern crate lazy_static;
+#[cfg(feature = "json")]
+extern crate serde_json;
+
+mod fetch;
+"#
+    );
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("lazy-static").is_some());
+    assert!(toml["features"].get("json").is_some());
+}
+
+#[test]
+fn fails_to_add_extern_crate_for_dev_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-add",
+        "add",
+        "my-package",
+        "--dev",
+        "--extern",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--extern` can only be used for \
+             dependencies that are always available to the crate root, not with `--dev`, \
+             `--build` or `--target`.",
+        )
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert!(toml.get("dev-dependencies").is_none());
+}
+
 #[test]
 fn no_argument() {
     assert_cli::Assert::command(&["target/debug/cargo-add", "add"])
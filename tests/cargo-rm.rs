@@ -1,4 +1,10 @@
 extern crate assert_cli;
+extern crate toml;
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
 
 mod utils;
 use utils::{clone_out_test, execute_command, get_toml};
@@ -127,6 +133,264 @@ ERROR: The dependency `semver` could not be found in `dev-dependencies`.",
         .unwrap();
 }
 
+#[test]
+fn remove_multiple_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    execute_command(&["rm", "docopt", "pad", "toml"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("docopt").is_none());
+    assert!(toml["dependencies"].get("pad").is_none());
+    assert!(toml["dependencies"].get("toml").is_none());
+    assert!(toml["dependencies"].get("semver").is_some());
+}
+
+#[test]
+fn remove_multiple_dependencies_fails_without_change() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "docopt",
+        "invalid_dependency_name",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .unwrap();
+
+    // nothing is written if one of the crates cannot be removed
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("docopt").is_some());
+}
+
+#[test]
+fn remove_target_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    let toml = get_toml(&manifest);
+    assert!(toml["target"]["cfg(unix)"]["dependencies"].get("libc").is_some());
+    assert!(toml["target"]["cfg(unix)"]["dev-dependencies"].get("tempdir").is_some());
+
+    execute_command(&["rm", "libc", "--target", "cfg(unix)"], &manifest);
+    execute_command(&["rm", "tempdir", "--dev", "--target", "cfg(unix)"], &manifest);
+
+    // the emptied target tables are removed as well
+    let toml = get_toml(&manifest);
+    assert!(toml.get("target").is_none());
+}
+
+#[test]
+fn remove_dependency_from_all_sections() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("semver").is_some());
+    assert!(toml["build-dependencies"].get("semver").is_some());
+
+    execute_command(&["rm", "semver", "libc", "--all-sections"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("semver").is_none());
+    assert!(toml.get("build-dependencies").is_none());
+    assert!(toml["target"]["cfg(unix)"].get("dependencies").is_none());
+    assert!(toml["target"]["cfg(unix)"]["dev-dependencies"].get("tempdir").is_some());
+}
+
+#[test]
+fn remove_dependency_from_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    execute_command(&["rm", "clippy"], &manifest);
+
+    // `default` was left empty and removed, `lint` still refers to `semver`
+    let toml = get_toml(&manifest);
+    assert!(toml["features"].get("default").is_none());
+    assert_eq!(
+        toml["features"]["lint"],
+        toml::Value::Array(vec![toml::Value::String("semver/serde".to_owned())])
+    );
+
+    // `semver` is still a build dependency, so the feature keeps referring to it
+    execute_command(&["rm", "semver"], &manifest);
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["features"]["lint"].as_array().unwrap().len(), 1);
+
+    execute_command(&["rm", "semver", "--build"], &manifest);
+    let toml = get_toml(&manifest);
+    assert!(toml.get("features").is_none());
+}
+
+#[test]
+fn remove_dependency_keeping_empty_features() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+
+    execute_command(&["rm", "clippy", "--keep-empty-features"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["features"]["default"].as_array().unwrap().len(), 0);
+}
+
+/// Write sources using some of the fixture's dependencies next to the manifest.
+fn write_sources(manifest: &str) {
+    let root = Path::new(manifest).parent().unwrap();
+    for &(path, source) in &[
+        (
+            "src/main.rs",
+            "extern crate docopt;\nuse semver::Version;\n\
+             fn main() {\n    let _ = toml::Value::Integer(1);\n    ::libc::abort();\n}\n",
+        ),
+        ("tests/cli.rs", "extern crate pad;\nuse tempdir::TempDir;\n"),
+        ("build.rs", "extern crate semver;\nfn main() {}\n"),
+    ] {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path)
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+    }
+}
+
+#[test]
+fn list_unused_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    write_sources(&manifest);
+
+    let output = process::Command::new("target/debug/cargo-rm")
+        .args(&["rm", "--unused"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "`regex` in `dev-dependencies` is unused
+`clippy` in `dependencies` is unused
+WARN: `pad` in `dependencies` is only used by tests, benches or examples. Consider making it a \
+         development dependency.
+`rustc-serialize` in `dependencies` is unused
+"
+    );
+
+    // Nothing was removed
+    let toml = get_toml(&manifest);
+    assert!(toml["dependencies"].get("rustc-serialize").is_some());
+}
+
+#[test]
+fn list_unused_dependencies_skipping_invalid_sources() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    write_sources(&manifest);
+    let invalid = Path::new(&manifest).parent().unwrap().join("src/latin1.rs");
+    File::create(&invalid)
+        .unwrap()
+        .write_all(b"// caf\xe9\nuse clippy::Lint;\n")
+        .unwrap();
+
+    let output = process::Command::new("target/debug/cargo-rm")
+        .args(&["rm", "--unused"])
+        .arg(format!("--manifest-path={}", &manifest))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("WARN: Skipping `{}`, which is not UTF-8\n", invalid.display())
+    );
+    // Only the unused dependencies are listed on stdout
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("not UTF-8"));
+    assert!(stdout.contains("`clippy` in `dependencies` is unused"));
+}
+
+#[test]
+fn remove_unused_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    write_sources(&manifest);
+
+    execute_command(&["rm", "--unused", "--remove"], &manifest);
+
+    let toml = get_toml(&manifest);
+    let dependencies = toml["dependencies"].as_table().unwrap();
+    assert_eq!(
+        dependencies.keys().collect::<Vec<_>>(),
+        vec!["docopt", "pad", "semver", "toml"]
+    );
+    assert!(toml.get("dev-dependencies").is_none());
+    assert!(toml["build-dependencies"].get("semver").is_some());
+    assert!(toml["target"]["cfg(unix)"]["dependencies"].get("libc").is_some());
+    assert!(toml["target"]["cfg(unix)"]["dev-dependencies"].get("tempdir").is_some());
+
+    // The removed optional dependency is no longer referenced by `[features]`
+    assert_eq!(
+        toml["features"]["lint"],
+        toml::Value::Array(vec![toml::Value::String("semver/serde".to_owned())])
+    );
+}
+
+#[test]
+fn remove_extern_crate_declarations() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    write_sources(&manifest);
+    let main = Path::new(&manifest).parent().unwrap().join("src/main.rs");
+
+    // `semver` is still a build dependency, so it stays declared
+    execute_command(&["rm", "docopt", "semver", "--extern"], &manifest);
+
+    let mut source = String::new();
+    File::open(&main)
+        .unwrap()
+        .read_to_string(&mut source)
+        .unwrap();
+    assert_eq!(
+        source,
+        "use semver::Version;\nfn main() {\n    let _ = toml::Value::Integer(1);\n    \
+         ::libc::abort();\n}\n"
+    );
+}
+
+#[test]
+fn fails_to_remove_with_invalid_target() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "libc",
+        "--target",
+        "cfg(unix",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Could not edit `Cargo.toml`.
+
+ERROR: The target `cfg(unix` is not a valid `cfg` expression: expected `)`, found the end of \
+             the expression.",
+        )
+        .unwrap();
+
+    let toml = get_toml(&manifest);
+    assert!(toml["target"]["cfg(unix)"]["dependencies"].get("libc").is_some());
+}
+
+#[test]
+fn remove_requires_unused() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/rm/Cargo.toml.sample");
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-rm",
+        "rm",
+        "docopt",
+        "--remove",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Could not edit `Cargo.toml`.
+
+ERROR: `--remove` can only be used with `--unused`.",
+        )
+        .unwrap();
+}
+
 #[test]
 fn no_argument() {
     assert_cli::Assert::command(&["target/debug/cargo-rm", "rm"])
@@ -135,7 +399,8 @@ fn no_argument() {
             r"Invalid arguments.
 
 Usage:
-    cargo rm <crate> [--dev|--build] [options]
+    cargo rm <crates>... [--dev|--build] [options]
+    cargo rm --unused [--remove] [options]
     cargo rm (-h|--help)
     cargo rm --version",
         )
@@ -150,7 +415,8 @@ fn unknown_flags() {
             r"Unknown flag: '--flag'
 
 Usage:
-    cargo rm <crate> [--dev|--build] [options]
+    cargo rm <crates>... [--dev|--build] [options]
+    cargo rm --unused [--remove] [options]
     cargo rm (-h|--help)
     cargo rm --version",
         )
@@ -1,11 +1,62 @@
 extern crate assert_cli;
 #[macro_use]
 extern crate pretty_assertions;
+extern crate serde_json;
 extern crate tempdir;
 extern crate toml;
 
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
 mod utils;
-use utils::{clone_out_test, execute_command, get_toml};
+use utils::{clone_out_test, clone_out_test_dir, execute_command, get_toml};
+
+/// The registry that `cargo upgrade` reads crates.io responses from in the tests that need the
+/// versions of actual crates, instead of making them up
+const REGISTRY: &'static str = "tests/fixtures/upgrade/registry";
+
+/// Run `cargo upgrade` with the given arguments and return what it printed.
+fn upgrade_output(args: &[&str], manifest: &str) -> String {
+    let mut command = process::Command::new("target/debug/cargo-upgrade");
+    command
+        .arg("upgrade")
+        .args(args)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1");
+    successful_output(&mut command)
+}
+
+/// Build `cargo upgrade` with the given arguments, reading the versions from `REGISTRY`.
+fn upgrade_from_registry(args: &[&str], manifest: &str) -> process::Command {
+    let mut command = process::Command::new("target/debug/cargo-upgrade");
+    command
+        .arg("upgrade")
+        .args(args)
+        .arg(format!("--manifest-path={}", manifest))
+        .env("CARGO_IS_TEST", "1")
+        .env("CARGO_EDIT_TEST_REGISTRY", REGISTRY);
+    command
+}
+
+/// Run `cargo upgrade` with the given arguments on the versions in `REGISTRY`, and return what
+/// it printed.
+fn upgrade_output_from_registry(args: &[&str], manifest: &str) -> String {
+    successful_output(&mut upgrade_from_registry(args, manifest))
+}
+
+/// Run a command that has to succeed, and return what it printed.
+fn successful_output(command: &mut process::Command) -> String {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
 
 // Verify that an upgraded Cargo.toml matches what we expect.
 #[test]
@@ -112,6 +163,706 @@ fn upgrade_optional_dependency() {
     );
 }
 
+#[test]
+fn upgrade_pinned_only_when_asked() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "=0.1.1"], &manifest);
+
+    assert_eq!(
+        upgrade_output(&[], &manifest),
+        "dependencies:\n    versioned-package =0.1.1 -> =0.1.1 unchanged\n"
+    );
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["versioned-package"].as_str(),
+        Some("=0.1.1")
+    );
+
+    execute_command(&["upgrade", "--pinned"], &manifest);
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["versioned-package"].as_str(),
+        Some("versioned-package--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn upgrade_compatible_only() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "~0.1"], &manifest);
+
+    // The simulated registry has nothing newer within `^0.1.0`
+    assert_eq!(
+        upgrade_output(&["--compatible"], &manifest),
+        "dependencies:\n    versioned-package ~0.1 -> ~0.1 unchanged\n"
+    );
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["versioned-package"].as_str(),
+        Some("~0.1")
+    );
+}
+
+#[test]
+fn upgrade_incompatible_only() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "0.1.1"], &manifest);
+
+    execute_command(&["upgrade", "--incompatible"], &manifest);
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["versioned-package"].as_str(),
+        Some("versioned-package--CURRENT_VERSION_TEST")
+    );
+}
+
+#[test]
+fn fails_to_upgrade_compatible_and_incompatible() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--compatible",
+        "--incompatible",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--compatible` and `--incompatible` cannot \
+             be used together.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn upgrade_to_lockfile() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/lockfile");
+
+    // No registry access, not even the simulated one
+    let output = process::Command::new("target/debug/cargo-upgrade")
+        .args(&["upgrade", "--to-lockfile"])
+        .arg(format!("--manifest-path={}", manifest))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Renamed dependencies are looked up by their package name, and the policy limits which of
+    // the locked versions can be picked.
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/lockfile/Cargo.toml.target")
+    );
+}
+
+#[test]
+fn upgrade_skips_excluded_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "versioned-package", "--vers", "0.1.1"], &manifest);
+    execute_command(&["add", "other", "--vers", "0.2"], &manifest);
+
+    execute_command(&["upgrade", "--exclude", "other"], &manifest);
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dependencies"]["versioned-package"].as_str(),
+        Some("versioned-package--CURRENT_VERSION_TEST")
+    );
+    assert_eq!(toml["dependencies"]["other"].as_str(), Some("0.2"));
+}
+
+#[test]
+fn upgrade_follows_metadata_policy() {
+    let (tmpdir, _) = clone_out_test_dir("tests/fixtures/upgrade/policy");
+    let manifest = tmpdir.path().join("member/Cargo.toml");
+    let manifest = manifest.to_str().unwrap();
+
+    // `renamed` is ignored by its package name in the workspace's metadata, and `excluded` by
+    // its package name on the command line.
+    assert_eq!(
+        upgrade_output(&["--exclude", "other-original"], manifest),
+        "dependencies:
+    versioned-package 0.1.1 -> versioned-package--CURRENT_VERSION_TEST unknown
+"
+    );
+    let toml = get_toml(manifest);
+    assert_eq!(toml["dependencies"]["renamed"]["version"].as_str(), Some("0.2"));
+    assert_eq!(toml["dependencies"]["excluded"]["version"].as_str(), Some("0.3"));
+    // The metadata stays under `[package]` when the manifest is written.
+    assert!(toml["package"]["metadata"]["cargo-upgrade"].get("patch-only").is_some());
+    assert!(toml.get("metadata").is_none());
+}
+
+#[test]
+fn fails_to_upgrade_with_invalid_policy() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/upgrade/Cargo.toml.invalid-policy");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `patch-only` in the cargo-upgrade metadata \
+             must be a list of crate names.",
+        )
+        .unwrap();
+}
+
+/// Run `git` in a directory, returning what it printed.
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = process::Command::new("git")
+        .args(&["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).trim().to_owned()
+}
+
+#[test]
+fn upgrade_git_dependencies() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    // A bare repository with a few tagged commits
+    let work = tmpdir.path().join("work");
+    fs::create_dir(&work).unwrap();
+    git(&work, &["init", "-q"]);
+    git(&work, &["commit", "-q", "--allow-empty", "-m", "first"]);
+    let first = git(&work, &["rev-parse", "HEAD"]);
+    git(&work, &["tag", "v0.1.0"]);
+    git(&work, &["commit", "-q", "--allow-empty", "-m", "second"]);
+    git(&work, &["tag", "-a", "v0.2.0", "-m", "v0.2.0"]);
+    git(&work, &["tag", "v1.0.0-beta.1"]);
+    git(&work, &["commit", "-q", "--allow-empty", "-m", "third"]);
+    let head = git(&work, &["rev-parse", "HEAD"]);
+    git(
+        tmpdir.path(),
+        &["clone", "-q", "--bare", "work", "repo.git"],
+    );
+
+    let repo = tmpdir.path().join("repo.git");
+    let repo = repo.to_str().unwrap();
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&manifest)
+        .unwrap()
+        .write_all(
+            format!(
+                r#"
+[dependencies]
+tagged = {{ git = "{repo}", tag = "v0.1.0" }}
+revved = {{ git = "{repo}", rev = "{rev}" }}
+branched = {{ git = "{repo}", branch = "master" }}
+"#,
+                repo = repo,
+                rev = &first[..7]
+            ).as_bytes(),
+        )
+        .unwrap();
+
+    assert_eq!(
+        upgrade_output(&["--git"], &manifest),
+        format!(
+            "dependencies:
+    revved {} -> {} unknown
+    tagged v0.1.0  -> v0.2.0  minor
+",
+            &first[..7],
+            &head[..7]
+        )
+    );
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["tagged"]["tag"].as_str(), Some("v0.2.0"));
+    assert_eq!(toml["dependencies"]["revved"]["rev"].as_str(), Some(&head[..7]));
+    assert!(toml["dependencies"]["branched"].get("rev").is_none());
+
+    execute_command(
+        &["upgrade", "--git", "--allow-prerelease", "-d", "tagged"],
+        &manifest,
+    );
+    assert_eq!(
+        get_toml(&manifest)["dependencies"]["tagged"]["tag"].as_str(),
+        Some("v1.0.0-beta.1")
+    );
+}
+
+#[test]
+fn upgrade_prerelease_within_its_series() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/prerelease");
+
+    // Only the dependency on a prerelease gets the newest prerelease of its `2.0.0` series
+    assert_eq!(
+        upgrade_output_from_registry(&[], &manifest),
+        "dependencies:
+    beta   2.0.0-beta.3 -> 2.0.0-rc.1 patch
+    stable 1.0          -> 1.2        minor
+"
+    );
+
+    // Prereleases of other series are only picked with `--allow-prerelease`, and only for the
+    // dependencies on a release
+    assert_eq!(
+        upgrade_output_from_registry(&["--allow-prerelease"], &manifest),
+        "dependencies:
+    beta   2.0.0-rc.1 -> 2.0.0-rc.1    unchanged
+    stable 1.2        -> 3.0.0-alpha.1 major
+"
+    );
+}
+
+#[test]
+fn upgrade_syncs_path_dependency_versions() {
+    let (tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/workspace");
+
+    assert_eq!(
+        upgrade_output(&[], &manifest),
+        "dependencies:
+    core                            0.3    -> 0.4    minor
+    pinned-core                     =0.3.2 -> =0.4.1 minor
+dev-dependencies in app/Cargo.toml:
+    cargo-upgrade-workspace-fixture 0.1    -> 0.1    unchanged
+dependencies in app/Cargo.toml:
+    core                            0.3    -> 0.4    minor
+build-dependencies in tools/Cargo.toml:
+    core                            0.2    -> 0.4    minor
+"
+    );
+
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/workspace/Cargo.toml.target")
+    );
+    // The other members are synced as well.
+    let app = get_toml(tmpdir.path().join("app/Cargo.toml").to_str().unwrap());
+    assert_eq!(app["dependencies"]["core"]["version"].as_str(), Some("0.4"));
+    assert_eq!(app["dependencies"]["core"]["path"].as_str(), Some("../core"));
+    let tools = get_toml(tmpdir.path().join("tools/Cargo.toml").to_str().unwrap());
+    assert_eq!(
+        tools["build-dependencies"]["core"]["version"].as_str(),
+        Some("0.4")
+    );
+}
+
+#[test]
+fn minimal_leaves_path_dependency_versions() {
+    let (tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/workspace");
+
+    assert_eq!(upgrade_output(&["--minimal"], &manifest), "");
+
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/workspace/Cargo.toml.sample")
+    );
+    let app = get_toml(tmpdir.path().join("app/Cargo.toml").to_str().unwrap());
+    assert_eq!(app["dependencies"]["core"]["version"].as_str(), Some("0.3"));
+}
+
+#[test]
+fn reports_upgraded_dependencies() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/report");
+
+    assert_eq!(
+        upgrade_output_from_registry(&[], &manifest),
+        "dev-dependencies:
+    alias             1.4.0 -> 1.4.2 patch
+    yanked-start      1.0   -> 1.1   minor
+dependencies:
+    versioned-package 0.1.1 -> 0.2.1 minor
+"
+    );
+}
+
+#[test]
+fn reports_upgraded_dependencies_as_json() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/report");
+
+    let report: serde_json::Value = serde_json::from_str(&upgrade_output_from_registry(
+        &["--format", "json", "-d", "versioned-package"],
+        &manifest,
+    )).unwrap();
+    assert_eq!(
+        report,
+        serde_json::from_str::<serde_json::Value>(
+            r#"{
+                "sections": [{
+                    "section": "dependencies",
+                    "dependencies": [{
+                        "name": "versioned-package",
+                        "old": "0.1.1",
+                        "new": "0.2.1",
+                        "kind": "minor"
+                    }]
+                }]
+            }"#
+        ).unwrap()
+    );
+}
+
+#[test]
+fn reports_upgraded_dependencies_as_json_with_normalized_names() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/normalized");
+
+    // The registry knows `serde-json` as `serde_json`, which is warned about on stderr only
+    let output = upgrade_from_registry(&["--format", "json"], &manifest)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("WARN: Added `serde_json` instead of `serde-json`")
+    );
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["sections"][0]["dependencies"][0]["new"].as_str(),
+        Some("1.0")
+    );
+}
+
+#[test]
+fn upgrade_workspace_patch_and_replace_sections() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/sections");
+
+    execute_command(&["upgrade"], &manifest);
+
+    // The `[replace]` key of `libc` is synced with the crate replacing it, as Cargo requires.
+    let upgraded = get_toml(&manifest);
+    let target = get_toml("tests/fixtures/upgrade/sections/Cargo.toml.target");
+    assert_eq!(target, upgraded);
+}
+
+/// Run `cargo upgrade --check` with the given arguments on the versions in `REGISTRY`, and
+/// return its exit status.
+fn check_status(args: &[&str], manifest: &str) -> Option<i32> {
+    upgrade_from_registry(&["--check"], manifest)
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn check_reports_upgrades_without_changing_the_manifest() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/check");
+
+    // Pinned requirements are not upgraded, and `1.1` already allows the latest `1.1.0`
+    assert_eq!(
+        check_status(&["--exclude", "minor", "--exclude", "patch"], &manifest),
+        Some(0)
+    );
+
+    assert_eq!(check_status(&[], &manifest), Some(2));
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/check/Cargo.toml.sample")
+    );
+}
+
+#[test]
+fn check_fails_on_threshold() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/check");
+
+    // `minor` goes from `1.0.2` to `1.1.0`, and `patch` from `1.4.0` to `1.4.2`
+    assert_eq!(check_status(&["--fail-on", "major"], &manifest), Some(0));
+    assert_eq!(check_status(&["--fail-on", "minor"], &manifest), Some(2));
+    assert_eq!(check_status(&["--fail-on", "patch"], &manifest), Some(2));
+    assert_eq!(
+        check_status(&["-d", "patch", "--fail-on", "minor"], &manifest),
+        Some(0)
+    );
+    assert_eq!(
+        check_status(&["-d", "patch", "--fail-on", "patch"], &manifest),
+        Some(2)
+    );
+}
+
+#[test]
+fn fails_to_check_with_unknown_threshold() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--check",
+        "--fail-on",
+        "build",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Unknown kind `build` for `--fail-on`, \
+             expected `major`, `minor` or `patch`.",
+        )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--fail-on",
+        "major",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--fail-on` can only be used with \
+             `--check`.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn upgrade_interactively() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/report");
+
+    let mut child = upgrade_from_registry(&["--interactive"], &manifest)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The dependencies are asked about by section, in alphabetical order. Versions are picked
+    // from the package of a renamed dependency, and yanked ones are asked for again.
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(b"1.4.1\n1.0.0\nn\n\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(
+        "dev-dependencies: yanked-start 1.0 -> 1.1 minor. Upgrade? [Y/n/<version>]"
+    ));
+    assert!(stderr.contains("No available version of `renamed` matches `=1.4.1`."));
+
+    let toml = get_toml(&manifest);
+    assert_eq!(
+        toml["dev-dependencies"]["alias"]["version"].as_str(),
+        Some("1.0.0")
+    );
+    assert_eq!(toml["dev-dependencies"]["yanked-start"].as_str(), Some("1.0"));
+    assert_eq!(toml["dependencies"]["versioned-package"].as_str(), Some("0.2.1"));
+}
+
+#[test]
+fn upgrade_affected_by_advisories() {
+    let (tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+    execute_command(&["add", "ammonia", "--vers", "1.0"], &manifest);
+    execute_command(&["add", "safe", "--vers", "1.0"], &manifest);
+    execute_command(&["add", "smallvec", "--vers", "=0.6.1"], &manifest);
+    execute_command(&["add", "unaffected", "--vers", "0.3"], &manifest);
+
+    let lock_file = Path::new(&manifest).parent().unwrap().join("Cargo.lock");
+    let mut lock = String::new();
+    for &(name, version) in &[
+        ("ammonia", "1.2.0"),
+        ("lazy", "0.1.0"),
+        ("safe", "1.0.0"),
+        ("smallvec", "0.6.1"),
+        ("unaffected", "0.3.2"),
+    ] {
+        lock.push_str(&format!(
+            "[[package]]\nname = \"{}\"\nversion = \"{}\"\n\
+             source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n",
+            name,
+            version
+        ));
+    }
+    fs::File::create(lock_file)
+        .unwrap()
+        .write_all(lock.as_bytes())
+        .unwrap();
+
+    let db = tmpdir.path().join("advisory-db");
+    let advisories = [
+        (
+            "ammonia/RUSTSEC-2019-0001.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-2019-0001\"\npackage = \"ammonia\"\n\
+             [versions]\npatched = [\">= 2.1.0\"]\n```\n\n# Uncontrolled recursion\n",
+        ),
+        (
+            "smallvec/RUSTSEC-2018-0003.toml",
+            "[advisory]\nid = \"RUSTSEC-2018-0003\"\npackage = \"smallvec\"\n\
+             patched_versions = [\">= 0.6.3\"]\n",
+        ),
+        (
+            "safe/RUSTSEC-2019-0002.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-2019-0002\"\npackage = \"safe\"\n\
+             [versions]\npatched = []\n```\n",
+        ),
+        ("README.md", "# RustSec Advisory Database\n"),
+        (
+            "lazy/RUSTSEC-2019-0003.md",
+            "```toml\n[advisory]\nid = \"RUSTSEC-2019-0003\"\npackage = \"lazy\"\n\
+             [versions]\npatched = [\">= 0.2.0\"]\n```\n",
+        ),
+    ];
+    // Without a `crates/` directory, the whole checkout is read.
+    for &(path, content) in &advisories {
+        let path = db.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::File::create(path)
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+    }
+
+    assert_eq!(
+        upgrade_output(&["--advisories", db.to_str().unwrap()], &manifest),
+        "dependencies:
+    ammonia  1.0    -> 2.1    major
+    smallvec =0.6.1 -> =0.6.3 patch
+unfixed advisories:
+    RUSTSEC-2019-0002 safe 1.0.0: no patched version
+    RUSTSEC-2019-0003 lazy 0.1.0: not a direct dependency
+"
+    );
+
+    let toml = get_toml(&manifest);
+    assert_eq!(toml["dependencies"]["ammonia"].as_str(), Some("2.1"));
+    assert_eq!(toml["dependencies"]["safe"].as_str(), Some("1.0"));
+    assert_eq!(toml["dependencies"]["smallvec"].as_str(), Some("=0.6.3"));
+    assert_eq!(toml["dependencies"]["unaffected"].as_str(), Some("0.3"));
+}
+
+#[test]
+fn upgrade_to_precise_versions() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/precise");
+
+    assert_eq!(
+        upgrade_output_from_registry(
+            &["--precise", "versioned-package@0.2.1", "--precise", "renamed@1.4.2"],
+            &manifest
+        ),
+        "dev-dependencies:
+    versioned-package 0.1   -> =0.2.1 minor
+build-dependencies:
+    alias             1.0   -> =1.4.2 minor
+dependencies:
+    versioned-package 0.1.1 -> =0.2.1 minor
+"
+    );
+
+    // Precise versions may be older than the current requirement
+    upgrade_output_from_registry(&["-d", "yanked-start", "--precise", "1.0.2"], &manifest);
+
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/precise/Cargo.toml.target")
+    );
+}
+
+#[test]
+fn fails_to_upgrade_to_yanked_precise_version() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/precise");
+
+    let output = upgrade_from_registry(&["--precise", "alias@1.4.1"], &manifest)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("No available version of `renamed` matches `=1.4.1`.")
+    );
+
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/precise/Cargo.toml.sample")
+    );
+}
+
+#[test]
+fn fails_to_upgrade_to_precise_version_of_missing_dependency() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--precise",
+        "missing@1.0.0",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: The dependency `missing` could not be found.",
+        )
+        .unwrap();
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--precise",
+        "1.0.0",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: `--precise 1.0.0` needs the dependencies to \
+             set it for, use `--dependency <dep>` or `<dep>@1.0.0`.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn raise_to_minimal_versions() {
+    let (_tmpdir, manifest) = clone_out_test_dir("tests/fixtures/upgrade/minimal");
+
+    // `yanked-start` 1.0.0 and 1.0.1, and `renamed` 1.4.1 were yanked, and there is no 1.4.0.
+    assert_eq!(
+        upgrade_output_from_registry(&["--minimal"], &manifest),
+        "dev-dependencies:
+    alias             ^1.4   -> ^1.4.2 patch
+dependencies:
+    versioned-package ~0.1.1 -> ~0.1.1 unchanged
+    yanked-start      1.0    -> 1.0.2  patch
+"
+    );
+    assert_eq!(
+        get_toml(&manifest),
+        get_toml("tests/fixtures/upgrade/minimal/Cargo.toml.target")
+    );
+}
+
+#[test]
+fn fails_to_upgrade_with_unknown_format() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--format",
+        "yaml",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Unknown format `yaml`, expected `text` or \
+             `json`.",
+        )
+        .unwrap();
+}
+
+#[test]
+fn fails_to_upgrade_with_invalid_date() {
+    let (_tmpdir, manifest) = clone_out_test("tests/fixtures/add/Cargo.toml.sample");
+
+    assert_cli::Assert::command(&[
+        "target/debug/cargo-upgrade",
+        "upgrade",
+        "--before",
+        "01/03/2024",
+        &format!("--manifest-path={}", manifest),
+    ]).fails_with(1)
+        .prints_error_exactly(
+            "Command failed due to unhandled error: Invalid date `01/03/2024`, expected a date \
+             like `2024-03-01`.",
+        )
+        .unwrap();
+}
+
 #[test]
 fn unknown_flags() {
     assert_cli::Assert::command(&["target/debug/cargo-upgrade", "upgrade", "foo", "--flag"])
@@ -120,7 +871,7 @@ fn unknown_flags() {
             r"Unknown flag: '--flag'
 
 Usage:
-    cargo upgrade [--dependency <dep>...] [--manifest-path <path>]
+    cargo upgrade [--dependency <dep>...] [--exclude <dep>...] [--precise <version>...] [options]
     cargo upgrade (-h | --help)
     cargo upgrade (-V | --version)",
         )
@@ -4,6 +4,7 @@ extern crate toml;
 use std::{fs, process};
 use std::ffi::OsStr;
 use std::io::prelude::*;
+use std::path::Path;
 
 /// Create temporary working directory with Cargo.toml mainifest
 pub fn clone_out_test(source: &str) -> (tempdir::TempDir, String) {
@@ -22,6 +23,35 @@ pub fn clone_out_test(source: &str) -> (tempdir::TempDir, String) {
     (tmpdir, path)
 }
 
+/// Create temporary working directory with a copy of a fixture directory, e.g. a crate with the
+/// crates its path dependencies point to
+///
+/// Files ending in `.sample` are copied without that suffix, so that the fixtures are not mistaken
+/// for parts of this crate. Returns the path of the `Cargo.toml` at the root of the copy.
+#[allow(dead_code)]
+pub fn clone_out_test_dir(source: &str) -> (tempdir::TempDir, String) {
+    fn copy_dir(source: &Path, target: &Path) {
+        fs::create_dir_all(target).unwrap();
+        for entry in fs::read_dir(source).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+            if path.is_dir() {
+                copy_dir(&path, &target.join(name));
+            } else {
+                fs::copy(&path, target.join(name.trim_right_matches(".sample")))
+                    .unwrap_or_else(|err| panic!("could not copy test fixture: {}", err));
+            }
+        }
+    }
+
+    let tmpdir =
+        tempdir::TempDir::new("cargo-add-test").expect("failed to construct temporary directory");
+    copy_dir(Path::new(source), tmpdir.path());
+    let path = tmpdir.path().join("Cargo.toml").to_str().unwrap().to_string();
+
+    (tmpdir, path)
+}
+
 /// Execute localc cargo command, includes `--manifest-path`
 pub fn execute_command<S>(command: &[S], manifest: &str)
 where